@@ -0,0 +1,230 @@
+use crate::api::Robot;
+use crate::exceptions::APIRequestError;
+use crate::media::{InputFile, MediaKind};
+use crate::send::message_id_of;
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::future::Future;
+
+#[derive(Debug)]
+pub struct AlbumItem {
+    pub kind: Option<MediaKind>,
+    pub file: InputFile,
+    pub file_name: Option<String>,
+}
+
+impl AlbumItem {
+    pub fn new(kind: Option<MediaKind>, file: InputFile) -> Self {
+        AlbumItem {
+            kind,
+            file,
+            file_name: None,
+        }
+    }
+
+    pub fn with_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlbumEntry {
+    pub file_id: String,
+    pub message_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AlbumResult {
+    pub entries: Vec<Result<AlbumEntry, APIRequestError>>,
+}
+
+impl AlbumResult {
+    pub fn sent(&self) -> impl Iterator<Item = &AlbumEntry> {
+        self.entries.iter().filter_map(|e| e.as_ref().ok())
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &APIRequestError)> {
+        self.entries.iter().enumerate().filter_map(|(i, e)| Some((i, e.as_ref().err()?)))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.entries.iter().all(|e| e.is_ok())
+    }
+
+    pub fn file_ids(&self) -> Vec<&str> {
+        self.sent().map(|e| e.file_id.as_str()).collect()
+    }
+
+    pub fn message_ids(&self) -> Vec<&str> {
+        self.sent().filter_map(|e| e.message_id.as_deref()).collect()
+    }
+}
+
+pub struct Album<'a> {
+    bot: &'a Robot,
+    chat_id: String,
+    items: Vec<AlbumItem>,
+    caption: Option<String>,
+    reply_to_message_id: Option<String>,
+    disable_notification: bool,
+    concurrency: usize,
+}
+
+impl<'a> Album<'a> {
+    pub(crate) fn new(bot: &'a Robot, chat_id: &str) -> Self {
+        Album {
+            bot,
+            chat_id: chat_id.to_string(),
+            items: Vec::new(),
+            caption: None,
+            reply_to_message_id: None,
+            disable_notification: false,
+            concurrency: 3,
+        }
+    }
+
+    pub fn item(mut self, item: AlbumItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    pub fn file(self, kind: Option<MediaKind>, file: InputFile) -> Self {
+        self.item(AlbumItem::new(kind, file))
+    }
+
+    pub fn image(self, file: InputFile) -> Self {
+        self.file(Some(MediaKind::Image), file)
+    }
+
+    pub fn document(self, file: InputFile) -> Self {
+        self.file(Some(MediaKind::File), file)
+    }
+
+    pub fn caption(mut self, caption: &str) -> Self {
+        self.caption = Some(caption.to_string());
+        self
+    }
+
+    pub fn reply_to(mut self, message_id: &str) -> Self {
+        self.reply_to_message_id = Some(message_id.to_string());
+        self
+    }
+
+    pub fn disable_notification(mut self, disable: bool) -> Self {
+        self.disable_notification = disable;
+        self
+    }
+
+    pub fn concurrency(mut self, max_concurrent_uploads: usize) -> Self {
+        self.concurrency = max_concurrent_uploads.max(1);
+        self
+    }
+
+    pub async fn send(self) -> Result<AlbumResult, APIRequestError> {
+        if self.items.is_empty() {
+            return Err(APIRequestError::RequestFailed("Album has no items".to_string()));
+        }
+
+        let bot = self.bot;
+        let uploads: Vec<Result<String, APIRequestError>> = futures_util::stream::iter(self.items)
+            .map(|item| async move {
+                let kind = match item.kind {
+                    Some(k) => k,
+                    None => item.file.media_kind().await,
+                };
+                bot.upload_file(kind, item.file, item.file_name.as_deref()).await
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let chat_id = self.chat_id.as_str();
+        let reply_to = self.reply_to_message_id.as_deref();
+        let disable_notification = self.disable_notification;
+        let entries = send_in_order(uploads, self.caption, |file_id, caption| async move {
+            bot.send_uploaded_file(
+                chat_id,
+                &file_id,
+                caption.as_deref(),
+                None,
+                None,
+                disable_notification,
+                reply_to,
+                None,
+            ).await
+        }).await;
+
+        Ok(AlbumResult { entries })
+    }
+}
+
+async fn send_in_order<F, Fut>(
+    uploads: Vec<Result<String, APIRequestError>>,
+    mut caption: Option<String>,
+    send: F,
+) -> Vec<Result<AlbumEntry, APIRequestError>>
+where
+    F: Fn(String, Option<String>) -> Fut,
+    Fut: Future<Output = Result<Value, APIRequestError>>,
+{
+    let mut entries = Vec::with_capacity(uploads.len());
+    for upload in uploads {
+        let file_id = match upload {
+            Ok(file_id) => file_id,
+            Err(e) => {
+                entries.push(Err(e));
+                continue;
+            }
+        };
+        let response = send(file_id.clone(), caption.clone()).await;
+        entries.push(response.map(|response| {
+            caption = None;
+            AlbumEntry {
+                message_id: message_id_of(&response).map(|id| id.to_string()),
+                file_id,
+            }
+        }));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn sends_in_order_with_caption_on_first_delivered_item() {
+        let calls = Mutex::new(Vec::new());
+        let uploads = vec![
+            Err(APIRequestError::UploadFailed("a".to_string())),
+            Ok("f1".to_string()),
+            Ok("f2".to_string()),
+            Ok("f3".to_string()),
+        ];
+
+        let entries = send_in_order(uploads, Some("Trip".to_string()), |file_id, caption| {
+            calls.lock().push((file_id.clone(), caption));
+            async move {
+                match file_id.as_str() {
+                    "f1" => Err(APIRequestError::RequestFailed("HTTP 500".to_string())),
+                    _ => Ok(json!({"status": "OK", "data": {"message_id": format!("m-{}", file_id)}})),
+                }
+            }
+        }).await;
+
+        assert_eq!(*calls.lock(), vec![
+            ("f1".to_string(), Some("Trip".to_string())),
+            ("f2".to_string(), Some("Trip".to_string())),
+            ("f3".to_string(), None),
+        ]);
+
+        let result = AlbumResult { entries };
+        assert!(!result.is_complete());
+        assert_eq!(result.file_ids(), vec!["f2", "f3"]);
+        assert_eq!(result.message_ids(), vec!["m-f2", "m-f3"]);
+        assert_eq!(result.errors().map(|(i, _)| i).collect::<Vec<_>>(), vec![0, 1]);
+    }
+}
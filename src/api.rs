@@ -1,17 +1,43 @@
-use crate::context::{Message, InlineMessage};
+use crate::album::Album;
+use crate::calendar::{CalendarDate, TEHRAN_UTC_OFFSET};
+use crate::callback_data::{CallbackCodec, CallbackData};
+use crate::context::{Button, InlineMessage, Keypad, Message};
+use crate::conversation::{WaitFilter, Waiters};
 use crate::exceptions::APIRequestError;
+use crate::i18n::I18n;
+use crate::file_cache::{CacheKeyMode, FileIdCache, FileIdStore};
+use crate::job_store::{JobStore, MisfirePolicy};
+use crate::jobs::{CronSchedule, JobHandle, Scheduler, Trigger};
+use crate::media::{InputFile, MediaKind};
+use crate::menu::Menu;
+use crate::normalize::TextNormalizer;
+use crate::pagination::Paginator;
+use crate::rich_text::{Caption, MessageText};
+use crate::send::{message_id_of, SendMessage};
+use crate::split::{rich_text_of, split_payload, MAX_CAPTION_LENGTH, MAX_TEXT_LENGTH};
+use crate::transfer::{RetryPolicy, Transfer};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
 use serde_json::{json, Value};
+use futures_util::TryStreamExt;
+use std::future::Future;
 use std::path::Path;
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::time::{sleep, Duration};
+use tokio_util::io::StreamReader;
+use tokio_util::sync::CancellationToken;
 
 const API_URL: &str = "https://botapi.rubika.ir/v3";
+const DELETE_MESSAGE_JOB: &str = "delete_message";
+
+static MEMORY_ONLY_DELETES: std::sync::Once = std::sync::Once::new();
 
 pub type MessageHandler = Box<dyn Fn(Arc<Robot>, Message) + Send + Sync>;
 pub type CallbackHandler = Box<dyn Fn(Arc<Robot>, Message) + Send + Sync>;
 pub type InlineQueryHandler = Box<dyn Fn(Arc<Robot>, InlineMessage) + Send + Sync>;
+pub type MessageFilter = Box<dyn Fn(&Message) -> bool + Send + Sync>;
 
 pub struct Robot {
     pub token: String,
@@ -22,10 +48,21 @@ pub struct Robot {
     pub platform: String,
     pub offset_id: Arc<RwLock<Option<String>>>,
     pub client: reqwest::Client,
+    pub transfer_client: reqwest::Client,
     pub sessions: Arc<RwLock<HashMap<String, HashMap<String, Value>>>>,
     pub message_handlers: Arc<RwLock<Vec<MessageHandler>>>,
     pub callback_handlers: Arc<RwLock<Vec<CallbackHandler>>>,
     pub inline_query_handler: Arc<RwLock<Option<InlineQueryHandler>>>,
+    pub scheduler: Scheduler,
+    pub shutdown: Arc<RwLock<CancellationToken>>,
+    pub file_cache: Arc<RwLock<Option<FileIdCache>>>,
+    pub upload_retry: Arc<RwLock<RetryPolicy>>,
+    pub validate_keypads: Arc<RwLock<bool>>,
+    pub callback_codec: Arc<RwLock<CallbackCodec>>,
+    pub waiters: Waiters,
+    pub split_long_text: Arc<RwLock<bool>>,
+    pub i18n: I18n,
+    pub text_normalizer: Arc<RwLock<Option<TextNormalizer>>>,
 }
 
 impl Robot {
@@ -41,6 +78,11 @@ impl Robot {
             .timeout(Duration::from_secs(timeout.unwrap_or(10)))
             .build()
             .expect("Failed to create HTTP client");
+        let transfer_client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(timeout.unwrap_or(10)))
+            .read_timeout(Duration::from_secs(timeout.unwrap_or(10)))
+            .build()
+            .expect("Failed to create HTTP client");
 
         let robot = Robot {
             token: token.clone(),
@@ -51,12 +93,25 @@ impl Robot {
             platform: platform.unwrap_or_else(|| "web".to_string()),
             offset_id: Arc::new(RwLock::new(None)),
             client,
+            transfer_client,
             sessions: Arc::new(RwLock::new(HashMap::new())),
             message_handlers: Arc::new(RwLock::new(Vec::new())),
             callback_handlers: Arc::new(RwLock::new(Vec::new())),
             inline_query_handler: Arc::new(RwLock::new(None)),
+            scheduler: Scheduler::new(),
+            shutdown: Arc::new(RwLock::new(CancellationToken::new())),
+            file_cache: Arc::new(RwLock::new(None)),
+            upload_retry: Arc::new(RwLock::new(RetryPolicy::default())),
+            validate_keypads: Arc::new(RwLock::new(true)),
+            callback_codec: Arc::new(RwLock::new(CallbackCodec::new())),
+            waiters: Waiters::new(),
+            split_long_text: Arc::new(RwLock::new(false)),
+            i18n: I18n::default(),
+            text_normalizer: Arc::new(RwLock::new(None)),
         };
 
+        robot.scheduler.register_kind(DELETE_MESSAGE_JOB, delete_message_job);
+
         crate::logger::log_info(&format!("Initialized RubikaBot with token: {}***", &token[..8.min(token.len())]));
         robot
     }
@@ -69,7 +124,7 @@ impl Robot {
         Arc::new(RwLock::new(sessions.get(chat_id).unwrap().clone()))
     }
 
-    async fn post(&self, method: &str, data: &Value) -> Result<Value, APIRequestError> {
+    pub(crate) async fn post(&self, method: &str, data: &Value) -> Result<Value, APIRequestError> {
         let url = format!("{}/{}/{}", API_URL, self.token, method);
         let response = self.client
             .post(&url)
@@ -92,13 +147,13 @@ impl Robot {
         Ok(json_resp)
     }
 
-    pub fn on_message<F>(&self, filters: Option<Box<dyn Fn(&Message) -> bool + Send + Sync>>, commands: Option<Vec<String>>, handler: F)
+    pub fn on_message<F>(&self, filters: Option<MessageFilter>, commands: Option<Vec<String>>, handler: F)
     where
         F: Fn(Arc<Robot>, Message) + Send + Sync + 'static,
     {
         let handler: MessageHandler = Box::new(move |bot, mut msg| {
             if let Some(ref cmds) = commands {
-                if let Some(ref text) = msg.text {
+                if let Some(ref text) = msg.normalized_text {
                     if !text.starts_with('/') {
                         return;
                     }
@@ -107,7 +162,7 @@ impl Robot {
                         return;
                     }
                     let cmd = &parts[0][1..];
-                    if !cmds.contains(&cmd.to_string()) {
+                    if !cmds.iter().any(|c| c == cmd || bot.normalize_text(c) == cmd) {
                         return;
                     }
                     msg.args = parts[1..].iter().map(|s| s.to_string()).collect();
@@ -115,7 +170,7 @@ impl Robot {
             }
 
             if let Some(ref filter) = filters {
-                if !filter(&msg) {
+                if !filter(&msg.for_filters()) {
                     return;
                 }
             }
@@ -150,6 +205,70 @@ impl Robot {
         self.callback_handlers.write().push(handler);
     }
 
+    pub fn on_callback_data<T, F>(&self, handler: F)
+    where
+        T: CallbackData,
+        F: Fn(Arc<Robot>, Message, T) + Send + Sync + 'static,
+    {
+        let handler: CallbackHandler = Box::new(move |bot, msg| {
+            let Some(button_id) = msg.aux_data.as_ref().and_then(|a| a.button_id.clone()) else {
+                return;
+            };
+            let codec = bot.callback_codec.read().clone();
+            if !codec.matches::<T>(&button_id) {
+                return;
+            }
+            match codec.decode::<T>(&button_id) {
+                Ok(data) => handler(bot.clone(), msg, data),
+                Err(e) => crate::logger::log_error(&format!("Ignoring callback {}: {}", button_id, e)),
+            }
+        });
+
+        self.callback_handlers.write().push(handler);
+    }
+
+    pub async fn wait_for(
+        &self,
+        chat_id: &str,
+        sender_id: Option<&str>,
+        filter: WaitFilter,
+        timeout: Duration,
+    ) -> Option<Message> {
+        self.waiters.wait(chat_id, sender_id, filter, timeout).await
+    }
+
+    pub fn set_callback_codec(&self, codec: CallbackCodec) {
+        *self.callback_codec.write() = codec;
+    }
+
+    pub fn callback_data<T: CallbackData>(&self, data: &T) -> Result<String, APIRequestError> {
+        self.callback_codec.read().encode(data)
+    }
+
+    pub fn callback_button<T: CallbackData>(&self, text: &str, data: &T) -> Result<Button, APIRequestError> {
+        Ok(Button::simple(&self.callback_data(data)?, text))
+    }
+
+    pub fn add_menu(&self, menu: Menu) -> Arc<Menu> {
+        let menu = Arc::new(menu);
+        let routed = Arc::clone(&menu);
+        self.on_callback(None, move |bot, msg| {
+            let menu = Arc::clone(&routed);
+            tokio::spawn(async move { menu.handle_callback(bot, msg).await });
+        });
+        menu
+    }
+
+    pub fn add_paginator<T: Send + Sync + 'static>(&self, paginator: Paginator<T>) -> Arc<Paginator<T>> {
+        let paginator = Arc::new(paginator);
+        let routed = Arc::clone(&paginator);
+        self.on_callback(None, move |bot, msg| {
+            let paginator = Arc::clone(&routed);
+            tokio::spawn(async move { paginator.handle_callback(bot, msg).await });
+        });
+        paginator
+    }
+
     pub fn on_inline_query<F>(&self, handler: F)
     where
         F: Fn(Arc<Robot>, InlineMessage) + Send + Sync + 'static,
@@ -161,6 +280,121 @@ impl Robot {
         *self.inline_query_handler.write() = Some(handler);
     }
 
+    pub fn run_once<F, Fut>(&self, name: Option<&str>, delay: Duration, callback: F) -> JobHandle
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler.schedule(Arc::new(self.clone()), name, Trigger::after(delay), callback)
+    }
+
+    pub fn run_at<F, Fut>(&self, name: Option<&str>, at: SystemTime, callback: F) -> JobHandle
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler.schedule(Arc::new(self.clone()), name, Trigger::At(at), callback)
+    }
+
+    pub fn run_repeating<F, Fut>(&self, name: Option<&str>, interval: Duration, callback: F) -> JobHandle
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler.schedule(Arc::new(self.clone()), name, Trigger::Interval(interval), callback)
+    }
+
+    pub fn run_cron<F, Fut>(&self, name: Option<&str>, expr: &str, callback: F) -> Result<JobHandle, APIRequestError>
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let trigger = Trigger::cron(expr)?;
+        Ok(self.scheduler.schedule(Arc::new(self.clone()), name, trigger, callback))
+    }
+
+    pub fn run_cron_jalali<F, Fut>(&self, name: Option<&str>, expr: &str, callback: F) -> Result<JobHandle, APIRequestError>
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let trigger = Trigger::Cron(CronSchedule::parse(expr)?.jalali());
+        Ok(self.scheduler.schedule(Arc::new(self.clone()), name, trigger, callback))
+    }
+
+    pub fn run_at_tehran<F, Fut>(
+        &self,
+        name: Option<&str>,
+        date: &CalendarDate,
+        hour: u32,
+        minute: u32,
+        callback: F,
+    ) -> Result<JobHandle, APIRequestError>
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let at = date.at(hour, minute, TEHRAN_UTC_OFFSET)
+            .ok_or_else(|| APIRequestError::RequestFailed(format!("Invalid time {:02}:{:02} on {}", hour, minute, date)))?;
+        Ok(self.run_at(name, at, callback))
+    }
+
+    pub fn register_job_kind<F, Fut>(&self, kind: &str, callback: F)
+    where
+        F: Fn(Arc<Robot>, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.scheduler.register_kind(kind, callback);
+    }
+
+    pub fn set_job_store<S: JobStore + 'static>(&self, store: S) {
+        self.scheduler.set_store(store);
+    }
+
+    pub fn run_persistent(
+        &self,
+        kind: &str,
+        name: Option<&str>,
+        payload: Value,
+        trigger: Trigger,
+        misfire: MisfirePolicy,
+    ) -> Result<JobHandle, APIRequestError> {
+        self.scheduler.schedule_persistent(Arc::new(self.clone()), kind, name, payload, trigger, misfire)
+    }
+
+    pub async fn restore_jobs(&self) -> Result<usize, APIRequestError> {
+        self.scheduler.restore(Arc::new(self.clone())).await
+    }
+
+    pub fn delete_message_after(
+        &self,
+        chat_id: &str,
+        message_id: &str,
+        delay: Duration,
+    ) -> Result<JobHandle, APIRequestError> {
+        let key = format!("{}:{}:{}", DELETE_MESSAGE_JOB, chat_id, message_id);
+        let payload = json!({"chat_id": chat_id, "message_id": message_id});
+
+        if self.scheduler.has_store() {
+            return self.run_persistent(
+                DELETE_MESSAGE_JOB,
+                Some(&key),
+                payload,
+                Trigger::after(delay),
+                MisfirePolicy::RunImmediately,
+            );
+        }
+
+        MEMORY_ONLY_DELETES.call_once(|| {
+            crate::logger::log_info("No job store is set; scheduled message deletions are kept in memory and lost on restart");
+        });
+        Ok(self.run_once(Some(&key), delay, move |bot| delete_message_job(bot, payload.clone())))
+    }
+
+    pub fn stop(&self) {
+        std::mem::take(&mut *self.shutdown.write()).cancel();
+    }
+
     async fn process_update(&self, update: &Value, bot: Arc<Robot>) {
         if let Some(update_type) = update.get("type").and_then(|v| v.as_str()) {
             if update_type == "ReceiveQuery" {
@@ -208,11 +442,12 @@ impl Robot {
                         Some(new_msg.clone()),
                     );
 
-                    if context.aux_data.is_some() {
-                        for handler in self.callback_handlers.read().iter() {
-                            handler(bot.clone(), context.clone());
-                            return;
-                        }
+                    if self.waiters.dispatch(&context) {
+                        return;
+                    }
+
+                    if context.aux_data.is_some() && self.dispatch_callback(&bot, &context) {
+                        return;
                     }
 
                     for handler in self.message_handlers.read().iter() {
@@ -223,31 +458,44 @@ impl Robot {
         }
     }
 
+    fn dispatch_callback(&self, bot: &Arc<Robot>, context: &Message) -> bool {
+        let callback_handlers = self.callback_handlers.read();
+        for handler in callback_handlers.iter() {
+            handler(bot.clone(), context.clone());
+        }
+        !callback_handlers.is_empty()
+    }
+
     pub async fn get_me(&self) -> Result<Value, APIRequestError> {
         self.post("getMe", &json!({})).await
     }
 
-    pub async fn send_message(
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_message(
         &self,
         chat_id: &str,
-        text: &str,
-        chat_keypad: Option<&Value>,
-        inline_keypad: Option<&Value>,
+        text: impl Into<MessageText>,
+        chat_keypad: Option<&Keypad>,
+        inline_keypad: Option<&Keypad>,
         disable_notification: bool,
         reply_to_message_id: Option<&str>,
         chat_keypad_type: Option<&str>,
-    ) -> Result<Value, APIRequestError> {
+    ) -> SendMessage<'_> {
+        if let Err(e) = self.check_keypads(&[chat_keypad, inline_keypad]) {
+            return SendMessage::failed(self, e);
+        }
+
         let mut payload = json!({
             "chat_id": chat_id,
-            "text": text,
             "disable_notification": disable_notification
         });
+        text.into().apply(&mut payload);
 
         if let Some(ck) = chat_keypad {
-            payload["chat_keypad"] = ck.clone();
+            payload["chat_keypad"] = json!(ck);
         }
         if let Some(ik) = inline_keypad {
-            payload["inline_keypad"] = ik.clone();
+            payload["inline_keypad"] = json!(ik);
         }
         if let Some(rtmi) = reply_to_message_id {
             payload["reply_to_message_id"] = json!(rtmi);
@@ -256,7 +504,7 @@ impl Robot {
             payload["chat_keypad_type"] = json!(cktype);
         }
 
-        self.post("sendMessage", &payload).await
+        SendMessage::new(self, payload)
     }
 
     pub async fn send_poll(
@@ -272,16 +520,19 @@ impl Robot {
         })).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn send_location(
         &self,
         chat_id: &str,
         latitude: &str,
         longitude: &str,
         disable_notification: bool,
-        inline_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
+        self.check_keypads(&[inline_keypad])?;
+
         let mut payload = json!({
             "chat_id": chat_id,
             "latitude": latitude,
@@ -290,7 +541,7 @@ impl Robot {
         });
 
         if let Some(ik) = inline_keypad {
-            payload["inline_keypad"] = ik.clone();
+            payload["inline_keypad"] = json!(ik);
         }
         if let Some(rtmi) = reply_to_message_id {
             payload["reply_to_message_id"] = json!(rtmi);
@@ -321,19 +572,98 @@ impl Robot {
         self.post("getChat", &json!({"chat_id": chat_id})).await
     }
 
-    pub async fn get_upload_url(&self, media_type: &str) -> Result<String, APIRequestError> {
-        let allowed = vec!["File", "Image", "Voice", "Music", "Gif"];
-        if !allowed.contains(&media_type) {
+    pub async fn get_file(&self, file_id: &str) -> Result<String, APIRequestError> {
+        let result = self.post("getFile", &json!({"file_id": file_id})).await?;
+        result.get("data")
+            .and_then(|d| d.get("download_url"))
+            .and_then(|v| v.as_str())
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string())
+            .ok_or_else(|| APIRequestError::RequestFailed(
+                format!("No download_url for file {}: {}", file_id, result)
+            ))
+    }
+
+    async fn open_download(&self, file_id: &str) -> Result<reqwest::Response, APIRequestError> {
+        let url = self.get_file(file_id).await?;
+        let response = self.transfer_client.get(&url).send().await?;
+        if !response.status().is_success() {
             return Err(APIRequestError::RequestFailed(
-                format!("Invalid media type. Must be one of {:?}", allowed)
+                format!("Download failed: {}", response.status())
             ));
         }
-        let result = self.post("requestSendFile", &json!({"type": media_type})).await?;
-        Ok(result.get("data")
+        Ok(response)
+    }
+
+    pub async fn download_to_bytes(&self, file_id: &str) -> Result<Vec<u8>, APIRequestError> {
+        self.download_to_bytes_with(file_id, &Transfer::default()).await
+    }
+
+    pub async fn download_to_bytes_with(
+        &self,
+        file_id: &str,
+        transfer: &Transfer,
+    ) -> Result<Vec<u8>, APIRequestError> {
+        let mut reader = self.download_stream_with(file_id, transfer).await?;
+        let mut data = Vec::new();
+        transfer.run(async {
+            reader.read_to_end(&mut data).await
+                .map_err(|e| download_error(transfer, e))
+        }).await?;
+        Ok(data)
+    }
+
+    pub async fn download_stream(
+        &self,
+        file_id: &str,
+    ) -> Result<impl AsyncRead + Send + Unpin + 'static, APIRequestError> {
+        self.download_stream_with(file_id, &Transfer::default()).await
+    }
+
+    pub async fn download_stream_with(
+        &self,
+        file_id: &str,
+        transfer: &Transfer,
+    ) -> Result<impl AsyncRead + Send + Unpin + 'static, APIRequestError> {
+        let response = transfer.run(self.open_download(file_id)).await?;
+        let total = response.content_length();
+        let stream = Box::pin(response.bytes_stream().map_err(std::io::Error::other));
+        Ok(StreamReader::new(transfer.track(stream, total)))
+    }
+
+    pub async fn download_file(&self, file_id: &str, path: impl AsRef<Path>) -> Result<u64, APIRequestError> {
+        self.download_file_with(file_id, path, &Transfer::default()).await
+    }
+
+    pub async fn download_file_with(
+        &self,
+        file_id: &str,
+        path: impl AsRef<Path>,
+        transfer: &Transfer,
+    ) -> Result<u64, APIRequestError> {
+        let path = path.as_ref();
+        let mut reader = self.download_stream_with(file_id, transfer).await?;
+        let mut file = tokio::fs::File::create(path).await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to create file: {}", e)))?;
+        let written = transfer.run(async {
+            tokio::io::copy(&mut reader, &mut file).await
+                .map_err(|e| download_error(transfer, e))
+        }).await?;
+        file.flush().await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to write file: {}", e)))?;
+        Ok(written)
+    }
+
+    pub async fn get_upload_url(&self, media_type: MediaKind) -> Result<String, APIRequestError> {
+        let result = self.post("requestSendFile", &json!({"type": media_type.as_str()})).await?;
+        result.get("data")
             .and_then(|d| d.get("upload_url"))
             .and_then(|v| v.as_str())
-            .unwrap_or_default()
-            .to_string())
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string())
+            .ok_or_else(|| APIRequestError::RequestFailed(
+                format!("No upload_url for {}: {}", media_type, result)
+            ))
     }
 
     pub async fn upload_media_file(
@@ -342,51 +672,182 @@ impl Robot {
         name: &str,
         path: &str,
     ) -> Result<String, APIRequestError> {
-        let file_bytes = if path.starts_with("http") {
-            let response = self.client.get(path).send().await?;
-            response.bytes().await?.to_vec()
-        } else {
-            tokio::fs::read(path).await
-                .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read file: {}", e)))?
+        self.upload_input_file(upload_url, name, InputFile::from(path)).await
+    }
+
+    pub async fn upload_input_file(
+        &self,
+        upload_url: &str,
+        name: &str,
+        file: InputFile,
+    ) -> Result<String, APIRequestError> {
+        self.upload_input_file_with(upload_url, name, file, &Transfer::default()).await
+    }
+
+    pub async fn upload_input_file_with(
+        &self,
+        upload_url: &str,
+        name: &str,
+        file: InputFile,
+        transfer: &Transfer,
+    ) -> Result<String, APIRequestError> {
+        transfer.run(async {
+            let mime = file.mime_type(Some(name)).await;
+            let part = file.into_part(&self.transfer_client, transfer).await?
+                .file_name(name.to_string())
+                .mime_str(mime)?;
+            let form = reqwest::multipart::Form::new().part("file", part);
+
+            let response = self.transfer_client
+                .post(upload_url)
+                .multipart(form)
+                .send()
+                .await
+                .map_err(|e| if transfer.is_cancelled() { APIRequestError::Cancelled } else { e.into() })?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(APIRequestError::from_upload_status(status.as_u16(), &body));
+            }
+
+            let data: Value = response.json().await?;
+            data.get("data")
+                .and_then(|d| d.get("file_id"))
+                .and_then(|v| v.as_str())
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string())
+                .ok_or_else(|| APIRequestError::UploadRejected(format!("No file_id in upload response: {}", data)))
+        }).await
+    }
+
+    pub async fn upload_file(
+        &self,
+        kind: MediaKind,
+        file: InputFile,
+        file_name: Option<&str>,
+    ) -> Result<String, APIRequestError> {
+        self.upload_file_with(kind, file, file_name, &Transfer::default()).await
+    }
+
+    pub async fn upload_file_with(
+        &self,
+        kind: MediaKind,
+        file: InputFile,
+        file_name: Option<&str>,
+        transfer: &Transfer,
+    ) -> Result<String, APIRequestError> {
+        let key = self.file_cache_key(kind, &file).await;
+        if let Some(id) = key.as_deref().and_then(|k| self.cached_file_id(k)) {
+            return Ok(id);
+        }
+        self.upload_uncached(kind, file, file_name, transfer, key).await
+    }
+
+    async fn upload_uncached(
+        &self,
+        kind: MediaKind,
+        file: InputFile,
+        file_name: Option<&str>,
+        transfer: &Transfer,
+        cache_key: Option<String>,
+    ) -> Result<String, APIRequestError> {
+        if let InputFile::FileId(id) = file {
+            return Ok(id);
+        }
+        let name = file_name
+            .map(|n| n.to_string())
+            .or_else(|| file.file_name())
+            .unwrap_or_else(|| kind.default_file_name().to_string());
+
+        let policy = self.upload_retry.read().clone();
+        let mut upload_url = transfer.run(self.get_upload_url(kind)).await?;
+        let mut original = Some(file);
+        let mut attempt = 1;
+
+        let file_id = loop {
+            let current = match original.as_ref().and_then(|f| f.try_clone()) {
+                Some(copy) if attempt < policy.max_attempts => copy,
+                _ => original.take().expect("upload input already consumed"),
+            };
+
+            match self.upload_input_file_with(&upload_url, &name, current, transfer).await {
+                Ok(file_id) => break file_id,
+                Err(e) if original.is_some() && e.is_transient() => {
+                    let delay = policy.delay_for(attempt);
+                    crate::logger::log_info(&format!(
+                        "Upload of {} failed (attempt {}/{}), retrying in {:?}: {}",
+                        name, attempt, policy.max_attempts, delay, e
+                    ));
+                    transfer.run(async {
+                        sleep(delay).await;
+                        Ok(())
+                    }).await?;
+                    if matches!(e, APIRequestError::UploadUrlExpired(_)) {
+                        upload_url = transfer.run(self.get_upload_url(kind)).await?;
+                    }
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
         };
 
-        let mut form = reqwest::multipart::Form::new();
-        let part = reqwest::multipart::Part::bytes(file_bytes)
-            .file_name(name.to_string())
-            .mime_str("application/octet-stream")?;
-        form = form.part("file", part);
+        if let (Some(key), Some(cache)) = (cache_key, self.file_cache.read().as_ref()) {
+            cache.put(&key, &file_id);
+        }
+        Ok(file_id)
+    }
 
-        let response = self.client
-            .post(upload_url)
-            .multipart(form)
-            .send()
-            .await?;
+    pub fn set_upload_retry(&self, policy: RetryPolicy) {
+        *self.upload_retry.write() = policy;
+    }
 
-        if !response.status().is_success() {
-            return Err(APIRequestError::RequestFailed(
-                format!("Upload failed: {}", response.status())
-            ));
+    pub fn enable_file_cache<S: FileIdStore + 'static>(&self, store: S, mode: CacheKeyMode) {
+        *self.file_cache.write() = Some(FileIdCache::new(store, mode));
+    }
+
+    pub fn disable_file_cache(&self) {
+        *self.file_cache.write() = None;
+    }
+
+    pub fn set_keypad_validation(&self, enabled: bool) {
+        *self.validate_keypads.write() = enabled;
+    }
+
+    pub(crate) fn check_keypads(&self, keypads: &[Option<&Keypad>]) -> Result<(), APIRequestError> {
+        if !*self.validate_keypads.read() {
+            return Ok(());
         }
+        keypads.iter().flatten().try_for_each(|keypad| keypad.check())
+    }
 
-        let data: Value = response.json().await?;
-        Ok(data.get("data")
-            .and_then(|d| d.get("file_id"))
-            .and_then(|v| v.as_str())
-            .unwrap_or_default()
-            .to_string())
+    pub fn set_split_long_text(&self, enabled: bool) {
+        *self.split_long_text.write() = enabled;
+    }
+
+    async fn file_cache_key(&self, kind: MediaKind, file: &InputFile) -> Option<String> {
+        let cache = self.file_cache.read().clone()?;
+        cache.key_for(kind, file).await
     }
 
-    async fn send_uploaded_file(
+    fn cached_file_id(&self, key: &str) -> Option<String> {
+        self.file_cache.read().as_ref().and_then(|c| c.get(key))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_uploaded_file(
         &self,
         chat_id: &str,
         file_id: &str,
-        text: Option<&str>,
-        chat_keypad: Option<&Value>,
-        inline_keypad: Option<&Value>,
+        text: impl Into<Caption>,
+        chat_keypad: Option<&Keypad>,
+        inline_keypad: Option<&Keypad>,
         disable_notification: bool,
         reply_to_message_id: Option<&str>,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
+        self.check_keypads(&[chat_keypad, inline_keypad])?;
+
         let mut payload = json!({
             "chat_id": chat_id,
             "file_id": file_id,
@@ -394,56 +855,94 @@ impl Robot {
             "chat_keypad_type": chat_keypad_type.unwrap_or("None")
         });
 
-        if let Some(t) = text {
-            payload["text"] = json!(t);
-        }
+        text.into().apply(&mut payload);
         if let Some(ck) = chat_keypad {
-            payload["chat_keypad"] = ck.clone();
+            payload["chat_keypad"] = json!(ck);
         }
         if let Some(ik) = inline_keypad {
-            payload["inline_keypad"] = ik.clone();
+            payload["inline_keypad"] = json!(ik);
         }
         if let Some(rtmi) = reply_to_message_id {
             payload["reply_to_message_id"] = json!(rtmi);
         }
 
-        self.post("sendFile", &payload).await
+        if !*self.split_long_text.read() || rich_text_of(&payload).utf16_len() <= MAX_CAPTION_LENGTH {
+            return self.post("sendFile", &payload).await;
+        }
+
+        let mut chunks = split_payload(&payload, MAX_CAPTION_LENGTH, MAX_TEXT_LENGTH).into_iter();
+        let mut file_payload = chunks.next().unwrap_or(payload);
+        file_payload["chat_keypad_type"] = json!("None");
+        let response = self.post("sendFile", &file_payload).await?;
+
+        let mut previous = message_id_of(&response).map(|id| id.to_string());
+        for mut chunk in chunks {
+            if let Some(map) = chunk.as_object_mut() {
+                map.remove("file_id");
+            }
+            if let Some(ref id) = previous {
+                chunk["reply_to_message_id"] = json!(id);
+            }
+            let sent = self.post("sendMessage", &chunk).await?;
+            previous = message_id_of(&sent).map(|id| id.to_string());
+        }
+        Ok(response)
     }
 
-    pub async fn send_document(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_file(
         &self,
         chat_id: &str,
-        path: Option<&str>,
-        file_id: Option<&str>,
-        text: Option<&str>,
+        kind: Option<MediaKind>,
+        file: InputFile,
+        text: impl Into<Caption>,
         file_name: Option<&str>,
-        inline_keypad: Option<&Value>,
-        chat_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         disable_notification: bool,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
-        let mut final_file_id = file_id.map(|s| s.to_string());
+        self.check_keypads(&[chat_keypad, inline_keypad])?;
 
-        if let Some(p) = path {
-            let name = file_name.unwrap_or_else(|| {
-                Path::new(p).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("file")
-            });
-            let upload_url = self.get_upload_url("File").await?;
-            final_file_id = Some(self.upload_media_file(&upload_url, name, p).await?);
-        }
+        let text = text.into();
+        let kind = match kind {
+            Some(k) => k,
+            None => file.media_kind().await,
+        };
 
-        if final_file_id.is_none() {
-            return Err(APIRequestError::RequestFailed(
-                "Either path or file_id must be provided".to_string()
-            ));
+        let key = self.file_cache_key(kind, &file).await;
+        if let Some(key) = key.as_deref() {
+            if let Some(cached_id) = self.cached_file_id(key) {
+                let response = self.send_uploaded_file(
+                    chat_id,
+                    &cached_id,
+                    text.clone(),
+                    chat_keypad,
+                    inline_keypad,
+                    disable_notification,
+                    reply_to_message_id,
+                    chat_keypad_type,
+                ).await;
+                let response = response?;
+                if !rejects_file_id(&response) {
+                    return Ok(response);
+                }
+                crate::logger::log_info(&format!(
+                    "Cached file_id {} was rejected ({}), uploading again",
+                    cached_id, response
+                ));
+                if let Some(cache) = self.file_cache.read().as_ref() {
+                    cache.remove(key);
+                }
+            }
         }
 
+        let file_id = self.upload_uncached(kind, file, file_name, &Transfer::default(), key).await?;
+
         self.send_uploaded_file(
             chat_id,
-            &final_file_id.unwrap(),
+            &file_id,
             text,
             chat_keypad,
             inline_keypad,
@@ -453,178 +952,172 @@ impl Robot {
         ).await
     }
 
-    pub async fn send_image(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_document(
         &self,
         chat_id: &str,
-        path: Option<&str>,
-        file_id: Option<&str>,
-        text: Option<&str>,
+        file: InputFile,
+        text: impl Into<Caption>,
         file_name: Option<&str>,
-        inline_keypad: Option<&Value>,
-        chat_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         disable_notification: bool,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
-        let mut final_file_id = file_id.map(|s| s.to_string());
-
-        if let Some(p) = path {
-            let name = file_name.unwrap_or_else(|| {
-                Path::new(p).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("image.jpg")
-            });
-            let upload_url = self.get_upload_url("Image").await?;
-            final_file_id = Some(self.upload_media_file(&upload_url, name, p).await?);
-        }
-
-        if final_file_id.is_none() {
-            return Err(APIRequestError::RequestFailed(
-                "Either path or file_id must be provided".to_string()
-            ));
-        }
-
-        self.send_uploaded_file(
+        self.send_file(
             chat_id,
-            &final_file_id.unwrap(),
+            Some(MediaKind::File),
+            file,
             text,
-            chat_keypad,
+            file_name,
             inline_keypad,
+            chat_keypad,
+            reply_to_message_id,
             disable_notification,
+            chat_keypad_type,
+        ).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_image(
+        &self,
+        chat_id: &str,
+        file: InputFile,
+        text: impl Into<Caption>,
+        file_name: Option<&str>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
+        reply_to_message_id: Option<&str>,
+        disable_notification: bool,
+        chat_keypad_type: Option<&str>,
+    ) -> Result<Value, APIRequestError> {
+        self.send_file(
+            chat_id,
+            Some(MediaKind::Image),
+            file,
+            text,
+            file_name,
+            inline_keypad,
+            chat_keypad,
             reply_to_message_id,
+            disable_notification,
             chat_keypad_type,
         ).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn send_music(
         &self,
         chat_id: &str,
-        path: Option<&str>,
-        file_id: Option<&str>,
-        text: Option<&str>,
+        file: InputFile,
+        text: impl Into<Caption>,
         file_name: Option<&str>,
-        inline_keypad: Option<&Value>,
-        chat_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         disable_notification: bool,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
-        let mut final_file_id = file_id.map(|s| s.to_string());
-
-        if let Some(p) = path {
-            let name = file_name.unwrap_or_else(|| {
-                Path::new(p).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("music.mp3")
-            });
-            let upload_url = self.get_upload_url("Music").await?;
-            final_file_id = Some(self.upload_media_file(&upload_url, name, p).await?);
-        }
-
-        if final_file_id.is_none() {
-            return Err(APIRequestError::RequestFailed(
-                "Either path or file_id must be provided".to_string()
-            ));
-        }
-
-        self.send_uploaded_file(
+        self.send_file(
             chat_id,
-            &final_file_id.unwrap(),
+            Some(MediaKind::Music),
+            file,
             text,
-            chat_keypad,
+            file_name,
             inline_keypad,
-            disable_notification,
+            chat_keypad,
             reply_to_message_id,
+            disable_notification,
             chat_keypad_type,
         ).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn send_voice(
         &self,
         chat_id: &str,
-        path: Option<&str>,
-        file_id: Option<&str>,
-        text: Option<&str>,
+        file: InputFile,
+        text: impl Into<Caption>,
         file_name: Option<&str>,
-        inline_keypad: Option<&Value>,
-        chat_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         disable_notification: bool,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
-        let mut final_file_id = file_id.map(|s| s.to_string());
-
-        if let Some(p) = path {
-            let name = file_name.unwrap_or_else(|| {
-                Path::new(p).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("voice.ogg")
-            });
-            let upload_url = self.get_upload_url("Voice").await?;
-            final_file_id = Some(self.upload_media_file(&upload_url, name, p).await?);
-        }
-
-        if final_file_id.is_none() {
-            return Err(APIRequestError::RequestFailed(
-                "Either path or file_id must be provided".to_string()
-            ));
-        }
-
-        self.send_uploaded_file(
+        self.send_file(
             chat_id,
-            &final_file_id.unwrap(),
+            Some(MediaKind::Voice),
+            file,
             text,
-            chat_keypad,
+            file_name,
             inline_keypad,
-            disable_notification,
+            chat_keypad,
             reply_to_message_id,
+            disable_notification,
             chat_keypad_type,
         ).await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn send_gif(
         &self,
         chat_id: &str,
-        path: Option<&str>,
-        file_id: Option<&str>,
-        text: Option<&str>,
+        file: InputFile,
+        text: impl Into<Caption>,
         file_name: Option<&str>,
-        inline_keypad: Option<&Value>,
-        chat_keypad: Option<&Value>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
         reply_to_message_id: Option<&str>,
         disable_notification: bool,
         chat_keypad_type: Option<&str>,
     ) -> Result<Value, APIRequestError> {
-        let mut final_file_id = file_id.map(|s| s.to_string());
-
-        if let Some(p) = path {
-            let name = file_name.unwrap_or_else(|| {
-                Path::new(p).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("animation.gif")
-            });
-            let upload_url = self.get_upload_url("Gif").await?;
-            final_file_id = Some(self.upload_media_file(&upload_url, name, p).await?);
-        }
-
-        if final_file_id.is_none() {
-            return Err(APIRequestError::RequestFailed(
-                "Either path or file_id must be provided".to_string()
-            ));
-        }
-
-        self.send_uploaded_file(
+        self.send_file(
             chat_id,
-            &final_file_id.unwrap(),
+            Some(MediaKind::Gif),
+            file,
             text,
-            chat_keypad,
+            file_name,
             inline_keypad,
+            chat_keypad,
+            reply_to_message_id,
             disable_notification,
+            chat_keypad_type,
+        ).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_video(
+        &self,
+        chat_id: &str,
+        file: InputFile,
+        text: impl Into<Caption>,
+        file_name: Option<&str>,
+        inline_keypad: Option<&Keypad>,
+        chat_keypad: Option<&Keypad>,
+        reply_to_message_id: Option<&str>,
+        disable_notification: bool,
+        chat_keypad_type: Option<&str>,
+    ) -> Result<Value, APIRequestError> {
+        self.send_file(
+            chat_id,
+            Some(MediaKind::Video),
+            file,
+            text,
+            file_name,
+            inline_keypad,
+            chat_keypad,
             reply_to_message_id,
+            disable_notification,
             chat_keypad_type,
         ).await
     }
 
+    pub fn album(&self, chat_id: &str) -> Album<'_> {
+        Album::new(self, chat_id)
+    }
+
     pub async fn get_updates(
         &self,
         offset_id: Option<&str>,
@@ -659,21 +1152,23 @@ impl Robot {
         &self,
         chat_id: &str,
         message_id: &str,
-        text: &str,
+        text: impl Into<MessageText>,
     ) -> Result<Value, APIRequestError> {
-        self.post("editMessageText", &json!({
+        let mut payload = json!({
             "chat_id": chat_id,
-            "message_id": message_id,
-            "text": text
-        })).await
+            "message_id": message_id
+        });
+        text.into().apply(&mut payload);
+        self.post("editMessageText", &payload).await
     }
 
     pub async fn edit_inline_keypad(
         &self,
         chat_id: &str,
         message_id: &str,
-        inline_keypad: &Value,
+        inline_keypad: &Keypad,
     ) -> Result<Value, APIRequestError> {
+        self.check_keypads(&[Some(inline_keypad)])?;
         self.post("editMessageKeypad", &json!({
             "chat_id": chat_id,
             "message_id": message_id,
@@ -681,6 +1176,18 @@ impl Robot {
         })).await
     }
 
+    pub async fn clear_inline_keypad(
+        &self,
+        chat_id: &str,
+        message_id: &str,
+    ) -> Result<Value, APIRequestError> {
+        self.post("editMessageKeypad", &json!({
+            "chat_id": chat_id,
+            "message_id": message_id,
+            "inline_keypad": {"rows": []}
+        })).await
+    }
+
     pub async fn delete_message(
         &self,
         chat_id: &str,
@@ -720,8 +1227,9 @@ impl Robot {
     pub async fn edit_chat_keypad(
         &self,
         chat_id: &str,
-        chat_keypad: &Value,
+        chat_keypad: &Keypad,
     ) -> Result<Value, APIRequestError> {
+        self.check_keypads(&[Some(chat_keypad)])?;
         self.post("editChatKeypad", &json!({
             "chat_id": chat_id,
             "chat_keypad_type": "New",
@@ -774,6 +1282,10 @@ impl Robot {
     pub async fn run(&self) -> Result<(), APIRequestError> {
         println!("Bot started running...");
         let bot = Arc::new(self.clone());
+        let shutdown = self.shutdown.read().clone();
+        if let Err(e) = self.scheduler.restore(bot.clone()).await {
+            crate::logger::log_error(&format!("Failed to restore scheduled jobs: {}", e));
+        }
 
         {
             let latest = self.get_updates(None, Some(100)).await?;
@@ -791,8 +1303,11 @@ impl Robot {
 
         loop {
             let offset = self.offset_id.read().clone();
-            let updates = self.get_updates(offset.as_deref(), Some(100)).await?;
-            
+            let updates = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                updates = self.get_updates(offset.as_deref(), Some(100)) => updates?,
+            };
+
             if let Some(data) = updates.get("data") {
                 if let Some(updates_array) = data.get("updates").and_then(|u| u.as_array()) {
                     for update in updates_array {
@@ -811,6 +1326,33 @@ impl Robot {
 
             sleep(Duration::from_millis(100)).await;
         }
+
+        crate::logger::log_info("Bot stopping, waiting for scheduled jobs");
+        self.scheduler.shutdown(Duration::from_secs(self.timeout)).await;
+        Ok(())
+    }
+}
+
+fn rejects_file_id(response: &Value) -> bool {
+    matches!(
+        response.get("status").and_then(|v| v.as_str()),
+        Some("INVALID_INPUT") | Some("NOT_FOUND")
+    )
+}
+
+async fn delete_message_job(bot: Arc<Robot>, payload: Value) {
+    let chat_id = payload.get("chat_id").and_then(|v| v.as_str()).unwrap_or_default();
+    let message_id = payload.get("message_id").and_then(|v| v.as_str()).unwrap_or_default();
+    if let Err(e) = bot.delete_message(chat_id, message_id).await {
+        crate::logger::log_error(&format!("Failed to auto-delete message {}: {}", message_id, e));
+    }
+}
+
+fn download_error(transfer: &Transfer, e: std::io::Error) -> APIRequestError {
+    if transfer.is_cancelled() {
+        APIRequestError::Cancelled
+    } else {
+        APIRequestError::RequestFailed(format!("Download failed: {}", e))
     }
 }
 
@@ -838,11 +1380,45 @@ impl Clone for Robot {
             platform: self.platform.clone(),
             offset_id: Arc::clone(&self.offset_id),
             client: self.client.clone(),
+            transfer_client: self.transfer_client.clone(),
             sessions: Arc::clone(&self.sessions),
             message_handlers: Arc::clone(&self.message_handlers),
             callback_handlers: Arc::clone(&self.callback_handlers),
             inline_query_handler: Arc::clone(&self.inline_query_handler),
+            scheduler: self.scheduler.clone(),
+            shutdown: self.shutdown.clone(),
+            file_cache: Arc::clone(&self.file_cache),
+            upload_retry: Arc::clone(&self.upload_retry),
+            validate_keypads: Arc::clone(&self.validate_keypads),
+            callback_codec: Arc::clone(&self.callback_codec),
+            waiters: self.waiters.clone(),
+            split_long_text: self.split_long_text.clone(),
+            i18n: self.i18n.clone(),
+            text_normalizer: self.text_normalizer.clone(),
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop_resets_the_shutdown_token() {
+        let robot = Robot::new("test-token".to_string(), None, None, None, None, None);
+        let running = robot.shutdown.read().clone();
+        robot.stop();
+        assert!(running.is_cancelled());
+        assert!(!robot.shutdown.read().is_cancelled());
+        assert!(!robot.clone().shutdown.read().is_cancelled());
+    }
+
+    #[test]
+    fn only_invalid_file_ids_are_evicted() {
+        assert!(rejects_file_id(&json!({"status": "INVALID_INPUT"})));
+        assert!(rejects_file_id(&json!({"status": "NOT_FOUND"})));
+        assert!(!rejects_file_id(&json!({"status": "OK"})));
+        assert!(!rejects_file_id(&json!({"status": "TOO_REQUESTS"})));
+        assert!(!rejects_file_id(&json!({})));
+    }
+}
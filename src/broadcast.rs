@@ -0,0 +1,480 @@
+use crate::api::Robot;
+use crate::exceptions::APIRequestError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::{watch, Notify};
+use tokio::time::{sleep, Duration, Instant};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+pub type ProgressCallback = Box<dyn Fn(&BroadcastProgress) + Send + Sync>;
+type DeliverFn = Box<dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<Value, APIRequestError>> + Send>> + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BroadcastContent {
+    Text(String),
+    File {
+        file_id: String,
+        text: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    Sent { message_id: Option<String> },
+    Blocked,
+    InvalidChat,
+    Failed(String),
+}
+
+impl DeliveryStatus {
+    pub fn is_sent(&self) -> bool {
+        matches!(self, DeliveryStatus::Sent { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResult {
+    pub chat_id: String,
+    pub status: DeliveryStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Checkpoint {
+    content: BroadcastContent,
+    chat_ids: Vec<String>,
+    position: usize,
+    results: Vec<ChatResult>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BroadcastProgress {
+    pub total: usize,
+    pub processed: usize,
+    pub sent: usize,
+    pub failed: usize,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BroadcastReport {
+    pub total: usize,
+    pub completed: bool,
+    pub results: Vec<ChatResult>,
+}
+
+impl BroadcastReport {
+    pub fn sent_count(&self) -> usize {
+        self.results.iter().filter(|r| r.status.is_sent()).count()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ChatResult> {
+        self.results.iter().filter(|r| !r.status.is_sent())
+    }
+
+    pub fn blocked(&self) -> Vec<&str> {
+        self.results.iter()
+            .filter(|r| r.status == DeliveryStatus::Blocked)
+            .map(|r| r.chat_id.as_str())
+            .collect()
+    }
+
+    pub fn invalid(&self) -> Vec<&str> {
+        self.results.iter()
+            .filter(|r| r.status == DeliveryStatus::InvalidChat)
+            .map(|r| r.chat_id.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastControl {
+    paused: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+}
+
+impl BroadcastControl {
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.wake.notify_waiters();
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.wake.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+pub struct Broadcast {
+    bot: Arc<Robot>,
+    state: Checkpoint,
+    per_second: u32,
+    checkpoint_path: Option<PathBuf>,
+    checkpoint_every: usize,
+    on_progress: Option<ProgressCallback>,
+    progress_tx: watch::Sender<BroadcastProgress>,
+    control: BroadcastControl,
+    deliver: Option<DeliverFn>,
+}
+
+impl Broadcast {
+    pub fn new(bot: Arc<Robot>, content: BroadcastContent, chat_ids: Vec<String>) -> Self {
+        let state = Checkpoint {
+            content,
+            chat_ids,
+            position: 0,
+            results: Vec::new(),
+        };
+        Self::from_state(bot, state)
+    }
+
+    pub fn text(bot: Arc<Robot>, text: &str, chat_ids: Vec<String>) -> Self {
+        Self::new(bot, BroadcastContent::Text(text.to_string()), chat_ids)
+    }
+
+    pub fn file(bot: Arc<Robot>, file_id: &str, text: Option<&str>, chat_ids: Vec<String>) -> Self {
+        Self::new(
+            bot,
+            BroadcastContent::File {
+                file_id: file_id.to_string(),
+                text: text.map(|t| t.to_string()),
+            },
+            chat_ids,
+        )
+    }
+
+    pub async fn resume_from(bot: Arc<Robot>, path: impl AsRef<Path>) -> Result<Self, APIRequestError> {
+        let path = path.as_ref();
+        let raw = tokio::fs::read(path).await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read checkpoint: {}", e)))?;
+        let state: Checkpoint = serde_json::from_slice(&raw)?;
+        let mut broadcast = Self::from_state(bot, state);
+        broadcast.checkpoint_path = Some(path.to_path_buf());
+        Ok(broadcast)
+    }
+
+    fn from_state(bot: Arc<Robot>, state: Checkpoint) -> Self {
+        let (progress_tx, _) = watch::channel(BroadcastProgress::default());
+        let broadcast = Broadcast {
+            bot,
+            state,
+            per_second: 20,
+            checkpoint_path: None,
+            checkpoint_every: 50,
+            on_progress: None,
+            progress_tx,
+            control: BroadcastControl::default(),
+            deliver: None,
+        };
+        let progress = broadcast.progress();
+        broadcast.progress_tx.send_replace(progress);
+        broadcast
+    }
+
+    pub fn rate_limit(mut self, messages_per_second: u32) -> Self {
+        self.per_second = messages_per_second.max(1);
+        self
+    }
+
+    pub fn checkpoint(mut self, path: impl AsRef<Path>, every: usize) -> Self {
+        self.checkpoint_path = Some(path.as_ref().to_path_buf());
+        self.checkpoint_every = every.max(1);
+        self
+    }
+
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BroadcastProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn deliver_with<F, Fut>(mut self, deliver: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, APIRequestError>> + Send + 'static,
+    {
+        self.deliver = Some(Box::new(move |chat_id| Box::pin(deliver(chat_id))));
+        self
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<BroadcastProgress> {
+        self.progress_tx.subscribe()
+    }
+
+    pub fn control(&self) -> BroadcastControl {
+        self.control.clone()
+    }
+
+    pub fn progress(&self) -> BroadcastProgress {
+        let sent = self.state.results.iter().filter(|r| r.status.is_sent()).count();
+        BroadcastProgress {
+            total: self.state.chat_ids.len(),
+            processed: self.state.position,
+            sent,
+            failed: self.state.results.len() - sent,
+            paused: self.control.is_paused(),
+        }
+    }
+
+    pub async fn run(mut self) -> Result<BroadcastReport, APIRequestError> {
+        let interval = Duration::from_secs_f64(1.0 / self.per_second as f64);
+        let mut next_send = Instant::now();
+
+        while self.state.position < self.state.chat_ids.len() {
+            if self.control.is_paused() {
+                self.save_checkpoint().await?;
+                self.report_progress();
+                while self.control.is_paused() && !self.control.is_stopped() {
+                    let notified = self.control.wake.notified();
+                    if !self.control.is_paused() || self.control.is_stopped() {
+                        break;
+                    }
+                    notified.await;
+                }
+            }
+            if self.control.is_stopped() {
+                break;
+            }
+
+            let chat_id = self.state.chat_ids[self.state.position].clone();
+            let mut attempt = 0;
+            let status = loop {
+                tokio::time::sleep_until(next_send).await;
+                next_send = Instant::now() + interval;
+                attempt += 1;
+
+                match self.deliver(&chat_id).await {
+                    Ok(response) => match classify(&response) {
+                        Some(status) => break status,
+                        None if attempt < MAX_ATTEMPTS => {
+                            crate::logger::log_debug(&format!("Broadcast rate limited on {}, backing off", chat_id));
+                            sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                        }
+                        None => break DeliveryStatus::Failed("TOO_REQUESTS".to_string()),
+                    },
+                    Err(APIRequestError::HttpError(e)) if attempt < MAX_ATTEMPTS => {
+                        crate::logger::log_debug(&format!("Broadcast to {} failed, retrying: {}", chat_id, e));
+                        sleep(Duration::from_secs(attempt as u64)).await;
+                    }
+                    Err(e) => break DeliveryStatus::Failed(e.to_string()),
+                }
+            };
+
+            self.state.results.push(ChatResult { chat_id, status });
+            self.state.position += 1;
+            self.report_progress();
+
+            if self.state.position % self.checkpoint_every == 0 {
+                self.save_checkpoint().await?;
+            }
+        }
+
+        self.save_checkpoint().await?;
+        self.report_progress();
+
+        let completed = self.state.position >= self.state.chat_ids.len();
+        crate::logger::log_info(&format!(
+            "Broadcast {}: {}/{} processed",
+            if completed { "finished" } else { "stopped" },
+            self.state.position,
+            self.state.chat_ids.len()
+        ));
+
+        Ok(BroadcastReport {
+            total: self.state.chat_ids.len(),
+            completed,
+            results: self.state.results,
+        })
+    }
+
+    async fn deliver(&self, chat_id: &str) -> Result<Value, APIRequestError> {
+        if let Some(ref deliver) = self.deliver {
+            return deliver(chat_id.to_string()).await;
+        }
+        match &self.state.content {
+            BroadcastContent::Text(text) => {
+                self.bot.send_message(chat_id, text, None, None, false, None, None).await
+            }
+            BroadcastContent::File { file_id, text } => {
+                self.bot.send_uploaded_file(
+                    chat_id,
+                    file_id,
+                    text.as_deref(),
+                    None,
+                    None,
+                    false,
+                    None,
+                    None,
+                ).await
+            }
+        }
+    }
+
+    fn report_progress(&self) {
+        let progress = self.progress();
+        if let Some(ref callback) = self.on_progress {
+            callback(&progress);
+        }
+        self.progress_tx.send_replace(progress);
+    }
+
+    async fn save_checkpoint(&self) -> Result<(), APIRequestError> {
+        let Some(ref path) = self.checkpoint_path else {
+            return Ok(());
+        };
+        let raw = serde_json::to_vec(&self.state)?;
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, raw).await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to write checkpoint: {}", e)))?;
+        tokio::fs::rename(&tmp, path).await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to write checkpoint: {}", e)))?;
+        Ok(())
+    }
+}
+
+fn classify(response: &Value) -> Option<DeliveryStatus> {
+    let status = response.get("status").and_then(|v| v.as_str()).unwrap_or("OK");
+    match status {
+        "OK" => Some(DeliveryStatus::Sent {
+            message_id: response.get("data")
+                .and_then(|d| d.get("message_id"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+        }),
+        "TOO_REQUESTS" => None,
+        "INVALID_INPUT" | "NOT_FOUND" => Some(DeliveryStatus::InvalidChat),
+        "NO_ACCESS" | "INVALID_ACCESS" | "FORBIDDEN" => Some(DeliveryStatus::Blocked),
+        other if other.contains("BLOCK") => Some(DeliveryStatus::Blocked),
+        other => Some(DeliveryStatus::Failed(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    fn robot() -> Arc<Robot> {
+        Arc::new(Robot::new("test-token".to_string(), None, None, None, None, None))
+    }
+
+    fn chats(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn classifies_api_statuses() {
+        let sent = classify(&json!({"status": "OK", "data": {"message_id": "42"}}));
+        assert_eq!(sent, Some(DeliveryStatus::Sent { message_id: Some("42".to_string()) }));
+        assert_eq!(classify(&json!({"status": "TOO_REQUESTS"})), None);
+        assert_eq!(classify(&json!({"status": "INVALID_INPUT"})), Some(DeliveryStatus::InvalidChat));
+        assert_eq!(classify(&json!({"status": "NO_ACCESS"})), Some(DeliveryStatus::Blocked));
+        assert_eq!(classify(&json!({"status": "USER_BLOCKED"})), Some(DeliveryStatus::Blocked));
+        assert_eq!(classify(&json!({"status": "SERVER_ERROR"})), Some(DeliveryStatus::Failed("SERVER_ERROR".to_string())));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_rate_limited_chats() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let report = Broadcast::text(robot(), "hi", chats(&["a", "b"]))
+            .deliver_with(move |chat_id| {
+                let call = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match (chat_id.as_str(), call) {
+                        ("a", 0 | 1) => Ok(json!({"status": "TOO_REQUESTS"})),
+                        ("a", _) => Ok(json!({"status": "OK"})),
+                        _ => Ok(json!({"status": "TOO_REQUESTS"})),
+                    }
+                }
+            })
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3 + MAX_ATTEMPTS as usize);
+        assert!(report.completed);
+        assert!(report.results[0].status.is_sent());
+        assert_eq!(report.results[1].status, DeliveryStatus::Failed("TOO_REQUESTS".to_string()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_http_errors_but_not_api_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let report = Broadcast::text(robot(), "hi", chats(&["a"]))
+            .deliver_with(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Err(APIRequestError::RequestFailed("HTTP 400".to_string())) }
+            })
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(report.results[0].status, DeliveryStatus::Failed(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resumes_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!("broadcast-{}.json", std::process::id()));
+        let delivered = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let log = delivered.clone();
+        let broadcast = Broadcast::text(robot(), "hi", chats(&["a", "b", "c", "d"])).checkpoint(&path, 1);
+        let control = broadcast.control();
+        let first = broadcast
+            .deliver_with(move |chat_id| {
+                let mut log = log.lock();
+                log.push(chat_id);
+                if log.len() == 2 {
+                    control.stop();
+                }
+                async { Ok(json!({"status": "OK"})) }
+            })
+            .run()
+            .await
+            .unwrap();
+        assert!(!first.completed);
+        assert_eq!(first.results.len(), 2);
+
+        let log = delivered.clone();
+        let resumed = Broadcast::resume_from(robot(), &path).await.unwrap();
+        assert_eq!(resumed.progress().processed, 2);
+        let report = resumed
+            .deliver_with(move |chat_id| {
+                log.lock().push(chat_id);
+                async { Ok(json!({"status": "OK"})) }
+            })
+            .run()
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(report.completed);
+        assert_eq!(report.sent_count(), 4);
+        assert_eq!(*delivered.lock(), chats(&["a", "b", "c", "d"]));
+    }
+}
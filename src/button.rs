@@ -1,69 +1,67 @@
-use serde_json::{json, Value};
+use crate::context::{
+    Button, ButtonCalendar, ButtonLocation, ButtonNumberPicker, ButtonPayment, ButtonSelection,
+    ButtonStringPicker, ButtonTextbox, ButtonType, Keypad, KeypadRow, Location,
+};
+use crate::calendar::{CalendarDate, CalendarType};
+use crate::exceptions::APIRequestError;
+use crate::i18n::{translate_keypad, Translator};
 
 pub struct InlineBuilder {
-    rows: Vec<Value>,
+    rows: Vec<KeypadRow>,
+    translator: Option<Translator>,
 }
 
 impl InlineBuilder {
     pub fn new() -> Self {
-        InlineBuilder { rows: Vec::new() }
+        InlineBuilder {
+            rows: Vec::new(),
+            translator: None,
+        }
     }
 
-    pub fn row(mut self, buttons: &[Value]) -> Self {
-        if buttons.is_empty() {
-            panic!("At least one button must be provided to row");
-        }
-        self.rows.push(json!({"buttons": buttons}));
+    pub fn translate<F>(mut self, translator: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.translator = Some(Box::new(translator));
         self
     }
 
-    pub fn button_simple(&self, id: &str, text: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "Simple",
-            "button_text": text
-        })
+    pub fn row(mut self, buttons: &[Button]) -> Self {
+        self.rows.push(KeypadRow { buttons: buttons.to_vec() });
+        self
     }
 
-    pub fn button_selection(&self, id: &str, text: &str, selection: &Value) -> Value {
-        json!({
-            "id": id,
-            "type": "Selection",
-            "button_text": text,
-            "button_selection": selection
-        })
+    pub fn button_simple(&self, id: &str, text: &str) -> Button {
+        Button::simple(id, text)
+    }
+
+    pub fn button_selection(&self, id: &str, text: &str, selection: &ButtonSelection) -> Button {
+        Button {
+            button_selection: Some(selection.clone()),
+            ..Button::new(id, ButtonType::Selection, text)
+        }
     }
 
     pub fn button_calendar(
         &self,
         id: &str,
         title: &str,
-        type_: &str,
-        default_value: Option<&str>,
-        min_year: Option<&str>,
-        max_year: Option<&str>,
-    ) -> Value {
-        let mut calendar = json!({
-            "title": title,
-            "type": type_
-        });
-        
-        if let Some(dv) = default_value {
-            calendar["default_value"] = json!(dv);
-        }
-        if let Some(my) = min_year {
-            calendar["min_year"] = json!(my);
-        }
-        if let Some(maxy) = max_year {
-            calendar["max_year"] = json!(maxy);
+        type_: CalendarType,
+        default_value: Option<&CalendarDate>,
+        min_year: Option<i32>,
+        max_year: Option<i32>,
+    ) -> Button {
+        Button {
+            button_calendar: Some(ButtonCalendar {
+                title: Some(title.to_string()),
+                type_: Some(type_),
+                default_value: default_value.map(|d| d.to_calendar(type_).to_string()),
+                min_year: min_year.map(|y| y.to_string()),
+                max_year: max_year.map(|y| y.to_string()),
+            }),
+            ..Button::new(id, ButtonType::Calendar, title)
         }
-
-        json!({
-            "id": id,
-            "type": "Calendar",
-            "button_text": title,
-            "button_calendar": calendar
-        })
     }
 
     pub fn button_number_picker(
@@ -73,23 +71,16 @@ impl InlineBuilder {
         min_value: &str,
         max_value: &str,
         default_value: Option<&str>,
-    ) -> Value {
-        let mut picker = json!({
-            "title": title,
-            "min_value": min_value,
-            "max_value": max_value
-        });
-        
-        if let Some(dv) = default_value {
-            picker["default_value"] = json!(dv);
+    ) -> Button {
+        Button {
+            button_number_picker: Some(ButtonNumberPicker {
+                title: Some(title.to_string()),
+                min_value: Some(min_value.to_string()),
+                max_value: Some(max_value.to_string()),
+                default_value: default_value.map(|v| v.to_string()),
+            }),
+            ..Button::new(id, ButtonType::NumberPicker, title)
         }
-
-        json!({
-            "id": id,
-            "type": "NumberPicker",
-            "button_text": title,
-            "button_number_picker": picker
-        })
     }
 
     pub fn button_string_picker(
@@ -98,24 +89,15 @@ impl InlineBuilder {
         title: Option<&str>,
         items: &[String],
         default_value: Option<&str>,
-    ) -> Value {
-        let mut picker = json!({
-            "items": items
-        });
-        
-        if let Some(dv) = default_value {
-            picker["default_value"] = json!(dv);
-        }
-        if let Some(t) = title {
-            picker["title"] = json!(t);
+    ) -> Button {
+        Button {
+            button_string_picker: Some(ButtonStringPicker {
+                items: items.to_vec(),
+                default_value: default_value.map(|v| v.to_string()),
+                title: title.map(|v| v.to_string()),
+            }),
+            ..Button::new(id, ButtonType::StringPicker, title.unwrap_or("choice"))
         }
-
-        json!({
-            "id": id,
-            "type": "StringPicker",
-            "button_text": title.unwrap_or("choice"),
-            "button_string_picker": picker
-        })
     }
 
     pub fn button_location(
@@ -123,31 +105,20 @@ impl InlineBuilder {
         id: &str,
         type_: &str,
         location_image_url: &str,
-        default_pointer_location: Option<&Value>,
-        default_map_location: Option<&Value>,
+        default_pointer_location: Option<&Location>,
+        default_map_location: Option<&Location>,
         title: Option<&str>,
-    ) -> Value {
-        let mut loc = json!({
-            "type": type_,
-            "location_image_url": location_image_url
-        });
-        
-        if let Some(dpl) = default_pointer_location {
-            loc["default_pointer_location"] = dpl.clone();
-        }
-        if let Some(dml) = default_map_location {
-            loc["default_map_location"] = dml.clone();
-        }
-        if let Some(t) = title {
-            loc["title"] = json!(t);
+    ) -> Button {
+        Button {
+            button_location: Some(ButtonLocation {
+                type_: Some(type_.to_string()),
+                location_image_url: Some(location_image_url.to_string()),
+                default_pointer_location: default_pointer_location.cloned(),
+                default_map_location: default_map_location.cloned(),
+                title: title.map(|v| v.to_string()),
+            }),
+            ..Button::new(id, ButtonType::Location, title.unwrap_or("location"))
         }
-
-        json!({
-            "id": id,
-            "type": "Location",
-            "button_text": title.unwrap_or("location"),
-            "button_location": loc
-        })
     }
 
     pub fn button_textbox(
@@ -158,28 +129,17 @@ impl InlineBuilder {
         type_keypad: &str,
         place_holder: Option<&str>,
         default_value: Option<&str>,
-    ) -> Value {
-        let mut textbox = json!({
-            "type_line": type_line,
-            "type_keypad": type_keypad
-        });
-        
-        if let Some(ph) = place_holder {
-            textbox["place_holder"] = json!(ph);
-        }
-        if let Some(dv) = default_value {
-            textbox["default_value"] = json!(dv);
-        }
-        if let Some(t) = title {
-            textbox["title"] = json!(t);
+    ) -> Button {
+        Button {
+            button_textbox: Some(ButtonTextbox {
+                type_line: Some(type_line.to_string()),
+                type_keypad: Some(type_keypad.to_string()),
+                place_holder: place_holder.map(|v| v.to_string()),
+                default_value: default_value.map(|v| v.to_string()),
+                title: title.map(|v| v.to_string()),
+            }),
+            ..Button::new(id, ButtonType::Textbox, title.unwrap_or("Text"))
         }
-
-        json!({
-            "id": id,
-            "type": "Textbox",
-            "button_text": title.unwrap_or("Text"),
-            "button_textbox": textbox
-        })
     }
 
     pub fn button_payment(
@@ -188,131 +148,87 @@ impl InlineBuilder {
         title: &str,
         amount: i32,
         description: Option<&str>,
-    ) -> Value {
-        let mut payment = json!({
-            "title": title,
-            "amount": amount
-        });
-        
-        if let Some(d) = description {
-            payment["description"] = json!(d);
+    ) -> Button {
+        Button {
+            button_payment: Some(ButtonPayment {
+                title: Some(title.to_string()),
+                amount,
+                description: description.map(|v| v.to_string()),
+            }),
+            ..Button::new(id, ButtonType::Payment, title)
         }
+    }
 
-        json!({
-            "id": id,
-            "type": "Payment",
-            "button_text": title,
-            "button_payment": payment
-        })
+    pub fn button_camera_image(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::CameraImage, title)
     }
 
-    pub fn button_camera_image(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "CameraImage",
-            "button_text": title
-        })
+    pub fn button_camera_video(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::CameraVideo, title)
     }
 
-    pub fn button_camera_video(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "CameraVideo",
-            "button_text": title
-        })
+    pub fn button_gallery_image(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::GalleryImage, title)
     }
 
-    pub fn button_gallery_image(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "GalleryImage",
-            "button_text": title
-        })
+    pub fn button_gallery_video(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::GalleryVideo, title)
     }
 
-    pub fn button_gallery_video(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "GalleryVideo",
-            "button_text": title
-        })
+    pub fn button_file(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::File, title)
     }
 
-    pub fn button_file(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "File",
-            "button_text": title
-        })
+    pub fn button_audio(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::Audio, title)
     }
 
-    pub fn button_audio(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "Audio",
-            "button_text": title
-        })
+    pub fn button_record_audio(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::RecordAudio, title)
     }
 
-    pub fn button_record_audio(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "RecordAudio",
-            "button_text": title
-        })
+    pub fn button_my_phone_number(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::MyPhoneNumber, title)
     }
 
-    pub fn button_my_phone_number(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "MyPhoneNumber",
-            "button_text": title
-        })
+    pub fn button_my_location(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::MyLocation, title)
     }
 
-    pub fn button_my_location(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "MyLocation",
-            "button_text": title
-        })
+    pub fn button_link(&self, id: &str, title: &str, url: &str) -> Button {
+        Button {
+            url: Some(url.to_string()),
+            ..Button::new(id, ButtonType::Link, title)
+        }
     }
 
-    pub fn button_link(&self, id: &str, title: &str, url: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "Link",
-            "button_text": title,
-            "url": url
-        })
+    pub fn button_ask_my_phone_number(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::AskMyPhoneNumber, title)
     }
 
-    pub fn button_ask_my_phone_number(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "AskMyPhoneNumber",
-            "button_text": title
-        })
+    pub fn button_ask_location(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::AskLocation, title)
     }
 
-    pub fn button_ask_location(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "AskLocation",
-            "button_text": title
-        })
+    pub fn button_barcode(&self, id: &str, title: &str) -> Button {
+        Button::new(id, ButtonType::Barcode, title)
     }
 
-    pub fn button_barcode(&self, id: &str, title: &str) -> Value {
-        json!({
-            "id": id,
-            "type": "Barcode",
-            "button_text": title
-        })
+    pub fn build(self) -> Result<Keypad, APIRequestError> {
+        let keypad = self.build_unchecked();
+        keypad.check()?;
+        Ok(keypad)
     }
 
-    pub fn build(self) -> Value {
-        json!({"rows": self.rows})
+    pub fn build_unchecked(mut self) -> Keypad {
+        if let Some(ref translator) = self.translator {
+            translate_keypad(&mut self.rows, translator);
+        }
+        Keypad {
+            rows: self.rows,
+            resize_keyboard: None,
+            on_time_keyboard: None,
+        }
     }
 }
 
@@ -321,4 +237,3 @@ impl Default for InlineBuilder {
         Self::new()
     }
 }
-
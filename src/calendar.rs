@@ -0,0 +1,331 @@
+use crate::exceptions::APIRequestError;
+use crate::normalize::TextNormalizer;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const TEHRAN_UTC_OFFSET: i64 = 12_600;
+
+pub const PERSIAN_MONTH_NAMES: [&str; 12] = [
+    "فروردین", "اردیبهشت", "خرداد", "تیر", "مرداد", "شهریور",
+    "مهر", "آبان", "آذر", "دی", "بهمن", "اسفند",
+];
+
+pub const GREGORIAN_MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CalendarType {
+    #[default]
+    #[serde(rename = "DateGregorian", alias = "gregorian", alias = "Gregorian")]
+    Gregorian,
+    #[serde(rename = "DatePersian", alias = "persian", alias = "Persian", alias = "jalali")]
+    Persian,
+}
+
+impl CalendarType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CalendarType::Gregorian => "DateGregorian",
+            CalendarType::Persian => "DatePersian",
+        }
+    }
+
+    pub fn month_name(&self, month: u32) -> Option<&'static str> {
+        let names = match self {
+            CalendarType::Gregorian => &GREGORIAN_MONTH_NAMES,
+            CalendarType::Persian => &PERSIAN_MONTH_NAMES,
+        };
+        names.get(month.checked_sub(1)? as usize).copied()
+    }
+
+    pub fn days_in_month(&self, year: i32, month: u32) -> Option<u32> {
+        let days = match (self, month) {
+            (_, 0) | (_, 13..) => return None,
+            (CalendarType::Gregorian, 2) if is_gregorian_leap(year) => 29,
+            (CalendarType::Gregorian, 2) => 28,
+            (CalendarType::Gregorian, 4 | 6 | 9 | 11) => 30,
+            (CalendarType::Gregorian, _) => 31,
+            (CalendarType::Persian, 1..=6) => 31,
+            (CalendarType::Persian, 7..=11) => 30,
+            (CalendarType::Persian, _) if is_jalali_leap(year) => 30,
+            (CalendarType::Persian, _) => 29,
+        };
+        Some(days)
+    }
+}
+
+impl std::fmt::Display for CalendarType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CalendarType {
+    type Err = APIRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+            .map_err(|_| APIRequestError::RequestFailed(format!("Invalid calendar type: {}", s)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "CalendarDateFields")]
+pub struct CalendarDate {
+    pub calendar: CalendarType,
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+#[derive(Deserialize)]
+struct CalendarDateFields {
+    #[serde(default)]
+    calendar: CalendarType,
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl TryFrom<CalendarDateFields> for CalendarDate {
+    type Error = String;
+
+    fn try_from(fields: CalendarDateFields) -> Result<Self, Self::Error> {
+        CalendarDate::new(fields.calendar, fields.year, fields.month, fields.day)
+            .ok_or_else(|| format!("invalid {} date {}-{}-{}", fields.calendar, fields.year, fields.month, fields.day))
+    }
+}
+
+impl CalendarDate {
+    pub fn new(calendar: CalendarType, year: i32, month: u32, day: u32) -> Option<Self> {
+        let valid = year >= 1 && calendar.days_in_month(year, month).is_some_and(|d| (1..=d).contains(&day));
+        valid.then_some(CalendarDate { calendar, year, month, day })
+    }
+
+    pub fn gregorian(year: i32, month: u32, day: u32) -> Option<Self> {
+        Self::new(CalendarType::Gregorian, year, month, day)
+    }
+
+    pub fn persian(year: i32, month: u32, day: u32) -> Option<Self> {
+        Self::new(CalendarType::Persian, year, month, day)
+    }
+
+    pub fn parse(value: &str, calendar: CalendarType) -> Option<Self> {
+        let value = TextNormalizer::new().normalize(value);
+        let parts: Vec<&str> = value.split(['-', '/', '.']).collect();
+        let [year, month, day] = parts.as_slice() else {
+            return None;
+        };
+        Self::new(calendar, year.trim().parse().ok()?, month.trim().parse().ok()?, day.trim().parse().ok()?)
+    }
+
+    pub fn from_days(days: i64, calendar: CalendarType) -> Self {
+        let (year, month, day) = civil_from_days(days);
+        let (year, month, day) = match calendar {
+            CalendarType::Gregorian => (year, month, day),
+            CalendarType::Persian => gregorian_to_jalali(year, month, day),
+        };
+        CalendarDate { calendar, year, month, day }
+    }
+
+    pub fn today(calendar: CalendarType, utc_offset: i64) -> Self {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default();
+        Self::from_days((secs + utc_offset).div_euclid(86_400), calendar)
+    }
+
+    pub fn days(&self) -> i64 {
+        let (year, month, day) = match self.calendar {
+            CalendarType::Gregorian => (self.year, self.month, self.day),
+            CalendarType::Persian => jalali_to_gregorian(self.year, self.month, self.day),
+        };
+        days_from_civil(year, month, day)
+    }
+
+    pub fn to_calendar(&self, calendar: CalendarType) -> Self {
+        match calendar == self.calendar {
+            true => *self,
+            false => Self::from_days(self.days(), calendar),
+        }
+    }
+
+    pub fn to_gregorian(&self) -> Self {
+        self.to_calendar(CalendarType::Gregorian)
+    }
+
+    pub fn to_persian(&self) -> Self {
+        self.to_calendar(CalendarType::Persian)
+    }
+
+    pub fn weekday(&self) -> u32 {
+        (self.days() + 4).rem_euclid(7) as u32
+    }
+
+    pub fn month_name(&self) -> &'static str {
+        self.calendar.month_name(self.month).unwrap_or_default()
+    }
+
+    pub fn add_days(&self, days: i64) -> Self {
+        Self::from_days(self.days() + days, self.calendar)
+    }
+
+    pub fn at(&self, hour: u32, minute: u32, utc_offset: i64) -> Option<SystemTime> {
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        let local = self.days() * 86_400 + (hour * 3600 + minute * 60) as i64;
+        let ts = u64::try_from(local - utc_offset).ok()?;
+        Some(UNIX_EPOCH + Duration::from_secs(ts))
+    }
+}
+
+impl std::fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn is_gregorian_leap(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn is_jalali_leap(year: i32) -> bool {
+    let (gy, gm, gd) = jalali_to_gregorian(year + 1, 1, 1);
+    let (ly, lm, ld) = jalali_to_gregorian(year, 12, 1);
+    days_from_civil(gy, gm, gd) - days_from_civil(ly, lm, ld) == 30
+}
+
+pub fn gregorian_to_jalali(year: i32, month: u32, day: u32) -> (i32, u32, u32) {
+    const MONTH_OFFSETS: [i64; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let gy = year as i64;
+    let gy2 = if month > 2 { gy + 1 } else { gy };
+    let mut days = 355_666 + 365 * gy + (gy2 + 3) / 4 - (gy2 + 99) / 100 + (gy2 + 399) / 400
+        + day as i64
+        + MONTH_OFFSETS[(month.clamp(1, 12) - 1) as usize];
+
+    let mut jy = -1595 + 33 * (days / 12_053);
+    days %= 12_053;
+    jy += 4 * (days / 1461);
+    days %= 1461;
+    if days > 365 {
+        jy += (days - 1) / 365;
+        days = (days - 1) % 365;
+    }
+    let (jm, jd) = match days < 186 {
+        true => (1 + days / 31, 1 + days % 31),
+        false => (7 + (days - 186) / 30, 1 + (days - 186) % 30),
+    };
+    (jy as i32, jm as u32, jd as u32)
+}
+
+pub fn jalali_to_gregorian(year: i32, month: u32, day: u32) -> (i32, u32, u32) {
+    let jy = year as i64 + 1595;
+    let jm = month as i64;
+    let mut days = -355_668 + 365 * jy + (jy / 33) * 8 + ((jy % 33) + 3) / 4 + day as i64
+        + if jm < 7 { (jm - 1) * 31 } else { (jm - 7) * 30 + 186 };
+
+    let mut gy = 400 * (days / 146_097);
+    days %= 146_097;
+    if days > 36_524 {
+        days -= 1;
+        gy += 100 * (days / 36_524);
+        days %= 36_524;
+        if days >= 365 {
+            days += 1;
+        }
+    }
+    gy += 4 * (days / 1461);
+    days %= 1461;
+    if days > 365 {
+        gy += (days - 1) / 365;
+        days = (days - 1) % 365;
+    }
+
+    let mut gd = days + 1;
+    let mut gm = 1;
+    while let Some(length) = CalendarType::Gregorian.days_in_month(gy as i32, gm) {
+        if gd <= length as i64 {
+            break;
+        }
+        gd -= length as i64;
+        gm += 1;
+    }
+    (gy as i32, gm, gd as u32)
+}
+
+pub(crate) fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_nowruz_between_calendars() {
+        let nowruz = CalendarDate::persian(1404, 1, 1).unwrap();
+        assert_eq!(nowruz.to_gregorian(), CalendarDate::gregorian(2025, 3, 21).unwrap());
+        assert_eq!(CalendarDate::gregorian(2024, 3, 20).unwrap().to_persian(), CalendarDate::persian(1403, 1, 1).unwrap());
+        assert_eq!(gregorian_to_jalali(1979, 2, 11), (1357, 11, 22));
+        assert_eq!(jalali_to_gregorian(1357, 11, 22), (1979, 2, 11));
+    }
+
+    #[test]
+    fn round_trips_every_day() {
+        for days in (-40_000..40_000).step_by(7) {
+            let persian = CalendarDate::from_days(days, CalendarType::Persian);
+            assert_eq!(persian.days(), days, "{}", persian);
+            assert!(CalendarDate::new(CalendarType::Persian, persian.year, persian.month, persian.day).is_some());
+        }
+    }
+
+    #[test]
+    fn knows_jalali_leap_years() {
+        assert!(is_jalali_leap(1399));
+        assert!(is_jalali_leap(1403));
+        assert!(!is_jalali_leap(1404));
+        assert!(is_jalali_leap(1408));
+        assert_eq!(CalendarType::Persian.days_in_month(1403, 12), Some(30));
+        assert_eq!(CalendarType::Persian.days_in_month(1404, 12), Some(29));
+        assert!(CalendarDate::persian(1404, 12, 30).is_none());
+    }
+
+    #[test]
+    fn parses_localized_digits() {
+        let date = CalendarDate::parse("۱۴۰۴/۰۱/۱۳", CalendarType::Persian).unwrap();
+        assert_eq!((date.year, date.month, date.day), (1404, 1, 13));
+        assert_eq!(date.to_string(), "1404-01-13");
+        assert_eq!(date.month_name(), "فروردین");
+        assert!(CalendarDate::parse("1404-13-01", CalendarType::Persian).is_none());
+    }
+
+    #[test]
+    fn computes_weekday_and_local_time() {
+        let nowruz = CalendarDate::persian(1404, 1, 1).unwrap();
+        assert_eq!(nowruz.weekday(), 5);
+        let at = nowruz.at(0, 0, TEHRAN_UTC_OFFSET).unwrap();
+        let expected = days_from_civil(2025, 3, 20) * 86_400 + 20 * 3600 + 30 * 60;
+        assert_eq!(at, UNIX_EPOCH + Duration::from_secs(expected as u64));
+        assert!(nowruz.at(24, 0, 0).is_none());
+    }
+}
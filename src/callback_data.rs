@@ -0,0 +1,363 @@
+use crate::exceptions::APIRequestError;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Impossible, Serializer};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+
+const SIGNATURE_BYTES: usize = 6;
+
+pub trait CallbackData: Serialize + DeserializeOwned {
+    const PREFIX: &'static str;
+}
+
+#[derive(Clone)]
+pub struct CallbackCodec {
+    secret: Option<Vec<u8>>,
+    max_length: Option<usize>,
+}
+
+impl CallbackCodec {
+    pub fn new() -> Self {
+        CallbackCodec {
+            secret: None,
+            max_length: Some(64),
+        }
+    }
+
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn max_length(mut self, max_length: Option<usize>) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn encode<T: CallbackData>(&self, data: &T) -> Result<String, APIRequestError> {
+        let fields = data.serialize(Positional)
+            .map_err(|e| APIRequestError::InvalidCallbackData(e.to_string()))?;
+        let body = match fields {
+            Value::Array(fields) => fields.iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+            other => serde_json::to_string(&other)?,
+        };
+
+        let mut id = format!("{}:{}", T::PREFIX, body);
+        if let Some(signature) = self.sign(&id) {
+            id.push('~');
+            id.push_str(&signature);
+        }
+
+        if let Some(max) = self.max_length {
+            if id.len() > max {
+                return Err(APIRequestError::InvalidCallbackData(format!(
+                    "encoded id is {} bytes, limit is {}",
+                    id.len(),
+                    max
+                )));
+            }
+        }
+        Ok(id)
+    }
+
+    pub fn matches<T: CallbackData>(&self, id: &str) -> bool {
+        id.strip_prefix(T::PREFIX).is_some_and(|rest| rest.starts_with(':'))
+    }
+
+    pub fn decode<T: CallbackData>(&self, id: &str) -> Result<T, APIRequestError> {
+        if !self.matches::<T>(id) {
+            return Err(APIRequestError::InvalidCallbackData(format!("{} is not a {} id", id, T::PREFIX)));
+        }
+
+        let signed = match self.secret {
+            Some(_) => {
+                let (signed, signature) = id.rsplit_once('~')
+                    .ok_or_else(|| APIRequestError::InvalidCallbackData("missing signature".to_string()))?;
+                if !self.verify(signed, signature) {
+                    return Err(APIRequestError::InvalidCallbackData("bad signature".to_string()));
+                }
+                signed
+            }
+            None => id,
+        };
+
+        let body = &signed[T::PREFIX.len() + 1..];
+        serde_json::from_str(&format!("[{}]", body))
+            .or_else(|_| serde_json::from_str(body))
+            .map_err(|e| APIRequestError::InvalidCallbackData(format!("cannot decode {}: {}", id, e)))
+    }
+
+    fn mac(&self, message: &str) -> Option<Hmac<Sha256>> {
+        let secret = self.secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).ok()?;
+        mac.update(message.as_bytes());
+        Some(mac)
+    }
+
+    fn sign(&self, message: &str) -> Option<String> {
+        let tag = self.mac(message)?.finalize().into_bytes();
+        Some(tag[..SIGNATURE_BYTES].iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn verify(&self, message: &str, signature: &str) -> bool {
+        let Some(bytes) = from_hex(signature).filter(|b| b.len() == SIGNATURE_BYTES) else {
+            return false;
+        };
+        self.mac(message).is_some_and(|mac| mac.verify_truncated_left(&bytes).is_ok())
+    }
+}
+
+impl Default for CallbackCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for CallbackCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackCodec")
+            .field("signed", &self.secret.is_some())
+            .field("max_length", &self.max_length)
+            .finish()
+    }
+}
+
+struct Positional;
+
+struct PositionalFields(Vec<Value>);
+
+macro_rules! forward_to_json {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(fn $method(self, v: $ty) -> Result<Value, serde_json::Error> {
+            serde_json::value::Serializer.$method(v)
+        })*
+    };
+}
+
+impl Serializer for Positional {
+    type Ok = Value;
+    type Error = serde_json::Error;
+    type SerializeSeq = <serde_json::value::Serializer as Serializer>::SerializeSeq;
+    type SerializeTuple = <serde_json::value::Serializer as Serializer>::SerializeTuple;
+    type SerializeTupleStruct = <serde_json::value::Serializer as Serializer>::SerializeTupleStruct;
+    type SerializeTupleVariant = <serde_json::value::Serializer as Serializer>::SerializeTupleVariant;
+    type SerializeMap = Impossible<Value, serde_json::Error>;
+    type SerializeStruct = PositionalFields;
+    type SerializeStructVariant = Impossible<Value, serde_json::Error>;
+
+    forward_to_json! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64),
+        serialize_f32(f32), serialize_f64(f64), serialize_char(char), serialize_str(&str), serialize_bytes(&[u8]),
+    }
+
+    fn serialize_none(self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, serde_json::Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<Value, serde_json::Error> {
+        serde_json::value::Serializer.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value, serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, serde_json::Error> {
+        serde_json::value::Serializer.serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, serde_json::Error> {
+        serde_json::value::Serializer.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, serde_json::Error> {
+        serde_json::value::Serializer.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, serde_json::Error> {
+        serde_json::value::Serializer.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, serde_json::Error> {
+        serde_json::value::Serializer.serialize_tuple_variant(name, index, variant, len)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, serde_json::Error> {
+        Err(ser::Error::custom("maps and flattened fields have no fixed position"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct, serde_json::Error> {
+        Ok(PositionalFields(Vec::with_capacity(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, serde_json::Error> {
+        Err(ser::Error::custom(format!("struct variant {} has no fixed position", variant)))
+    }
+}
+
+impl ser::SerializeStruct for PositionalFields {
+    type Ok = Value;
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) -> Result<(), serde_json::Error> {
+        self.0.push(serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), serde_json::Error> {
+        Err(ser::Error::custom(format!("field {} is skipped, callback data fields must always be serialized", key)))
+    }
+
+    fn end(self) -> Result<Value, serde_json::Error> {
+        Ok(Value::Array(self.0))
+    }
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Delete {
+        id: u64,
+        hard: bool,
+    }
+
+    impl CallbackData for Delete {
+        const PREFIX: &'static str = "del";
+    }
+
+    #[test]
+    fn encodes_compact_ids() {
+        let codec = CallbackCodec::new();
+        let id = codec.encode(&Delete { id: 1234, hard: true }).unwrap();
+        assert_eq!(id, "del:1234,true");
+        assert_eq!(codec.decode::<Delete>(&id).unwrap(), Delete { id: 1234, hard: true });
+        assert!(codec.decode::<Delete>("edit:1234,true").is_err());
+    }
+
+    #[test]
+    fn signs_and_verifies_with_secret() {
+        let codec = CallbackCodec::new().secret("top secret");
+        let id = codec.encode(&Delete { id: 7, hard: false }).unwrap();
+        assert!(id.starts_with("del:7,false~"));
+        assert_eq!(codec.decode::<Delete>(&id).unwrap(), Delete { id: 7, hard: false });
+
+        let forged = id.replacen("7", "8", 1);
+        assert!(codec.decode::<Delete>(&forged).is_err());
+        assert!(codec.decode::<Delete>("del:7,false").is_err());
+        assert!(CallbackCodec::new().secret("other").decode::<Delete>(&id).is_err());
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Move {
+        to: u32,
+        from: u32,
+        note: Option<String>,
+    }
+
+    impl CallbackData for Move {
+        const PREFIX: &'static str = "mv";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Sparse {
+        id: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        page: Option<u32>,
+    }
+
+    impl CallbackData for Sparse {
+        const PREFIX: &'static str = "sp";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Flat {
+        #[serde(flatten)]
+        inner: Delete,
+    }
+
+    impl CallbackData for Flat {
+        const PREFIX: &'static str = "fl";
+    }
+
+    #[test]
+    fn keeps_declaration_order() {
+        let codec = CallbackCodec::new();
+        let data = Move { to: 9, from: 1, note: None };
+        let id = codec.encode(&data).unwrap();
+        assert_eq!(id, "mv:9,1,null");
+        assert_eq!(codec.decode::<Move>(&id).unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_shapes_without_fixed_positions() {
+        let codec = CallbackCodec::new();
+        assert!(matches!(
+            codec.encode(&Sparse { id: 1, page: None }),
+            Err(APIRequestError::InvalidCallbackData(_))
+        ));
+        assert!(codec.encode(&Sparse { id: 1, page: Some(2) }).is_ok());
+        assert!(matches!(
+            codec.encode(&Flat { inner: Delete { id: 1, hard: true } }),
+            Err(APIRequestError::InvalidCallbackData(_))
+        ));
+    }
+
+    #[test]
+    fn enforces_max_length() {
+        let codec = CallbackCodec::new().max_length(Some(8));
+        assert!(matches!(
+            codec.encode(&Delete { id: 123_456_789, hard: true }),
+            Err(APIRequestError::InvalidCallbackData(_))
+        ));
+        assert!(CallbackCodec::new().max_length(None).encode(&Delete { id: u64::MAX, hard: true }).is_ok());
+    }
+}
@@ -92,122 +92,231 @@ pub struct AuxData {
     pub button_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonTextbox {
-    #[serde(rename = "type_line")]
+    #[serde(rename = "type_line", skip_serializing_if = "Option::is_none")]
     pub type_line: Option<String>,
-    #[serde(rename = "type_keypad")]
+    #[serde(rename = "type_keypad", skip_serializing_if = "Option::is_none")]
     pub type_keypad: Option<String>,
-    #[serde(rename = "place_holder")]
+    #[serde(rename = "place_holder", skip_serializing_if = "Option::is_none")]
     pub place_holder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
-    #[serde(rename = "default_value")]
+    #[serde(rename = "default_value", skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonNumberPicker {
-    #[serde(rename = "min_value")]
+    #[serde(rename = "min_value", skip_serializing_if = "Option::is_none")]
     pub min_value: Option<String>,
-    #[serde(rename = "max_value")]
+    #[serde(rename = "max_value", skip_serializing_if = "Option::is_none")]
     pub max_value: Option<String>,
-    #[serde(rename = "default_value")]
+    #[serde(rename = "default_value", skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonStringPicker {
     pub items: Vec<String>,
-    #[serde(rename = "default_value")]
+    #[serde(rename = "default_value", skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonCalendar {
-    #[serde(rename = "default_value")]
+    #[serde(rename = "default_value", skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
-    #[serde(rename = "type")]
-    pub type_: Option<String>,
-    #[serde(rename = "min_year")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<crate::calendar::CalendarType>,
+    #[serde(rename = "min_year", skip_serializing_if = "Option::is_none")]
     pub min_year: Option<String>,
-    #[serde(rename = "max_year")]
+    #[serde(rename = "max_year", skip_serializing_if = "Option::is_none")]
     pub max_year: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonLocation {
-    #[serde(rename = "default_pointer_location")]
-    pub default_pointer_location: Location,
-    #[serde(rename = "default_map_location")]
-    pub default_map_location: Location,
-    #[serde(rename = "type")]
+    #[serde(rename = "default_pointer_location", skip_serializing_if = "Option::is_none")]
+    pub default_pointer_location: Option<Location>,
+    #[serde(rename = "default_map_location", skip_serializing_if = "Option::is_none")]
+    pub default_map_location: Option<Location>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub type_: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
-    #[serde(rename = "location_image_url")]
+    #[serde(rename = "location_image_url", skip_serializing_if = "Option::is_none")]
     pub location_image_url: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonSelectionItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
-    #[serde(rename = "image_url")]
+    #[serde(rename = "image_url", skip_serializing_if = "Option::is_none")]
     pub image_url: Option<String>,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub type_: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ButtonSelection {
-    #[serde(rename = "selection_id")]
+    #[serde(rename = "selection_id", skip_serializing_if = "Option::is_none")]
     pub selection_id: Option<String>,
-    #[serde(rename = "search_type")]
+    #[serde(rename = "search_type", skip_serializing_if = "Option::is_none")]
     pub search_type: Option<String>,
-    #[serde(rename = "get_type")]
+    #[serde(rename = "get_type", skip_serializing_if = "Option::is_none")]
     pub get_type: Option<String>,
     pub items: Vec<ButtonSelectionItem>,
-    #[serde(rename = "is_multi_selection")]
+    #[serde(rename = "is_multi_selection", skip_serializing_if = "Option::is_none")]
     pub is_multi_selection: Option<bool>,
-    #[serde(rename = "columns_count")]
+    #[serde(rename = "columns_count", skip_serializing_if = "Option::is_none")]
     pub columns_count: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ButtonPayment {
+    pub title: Option<String>,
+    pub amount: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ButtonType {
+    #[default]
+    Simple,
+    Selection,
+    Calendar,
+    NumberPicker,
+    StringPicker,
+    Location,
+    Payment,
+    CameraImage,
+    CameraVideo,
+    GalleryImage,
+    GalleryVideo,
+    File,
+    Audio,
+    RecordAudio,
+    MyPhoneNumber,
+    MyLocation,
+    Textbox,
+    Link,
+    AskMyPhoneNumber,
+    AskLocation,
+    Barcode,
+}
+
+impl ButtonType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ButtonType::Simple => "Simple",
+            ButtonType::Selection => "Selection",
+            ButtonType::Calendar => "Calendar",
+            ButtonType::NumberPicker => "NumberPicker",
+            ButtonType::StringPicker => "StringPicker",
+            ButtonType::Location => "Location",
+            ButtonType::Payment => "Payment",
+            ButtonType::CameraImage => "CameraImage",
+            ButtonType::CameraVideo => "CameraVideo",
+            ButtonType::GalleryImage => "GalleryImage",
+            ButtonType::GalleryVideo => "GalleryVideo",
+            ButtonType::File => "File",
+            ButtonType::Audio => "Audio",
+            ButtonType::RecordAudio => "RecordAudio",
+            ButtonType::MyPhoneNumber => "MyPhoneNumber",
+            ButtonType::MyLocation => "MyLocation",
+            ButtonType::Textbox => "Textbox",
+            ButtonType::Link => "Link",
+            ButtonType::AskMyPhoneNumber => "AskMyPhoneNumber",
+            ButtonType::AskLocation => "AskLocation",
+            ButtonType::Barcode => "Barcode",
+        }
+    }
+}
+
+impl std::fmt::Display for ButtonType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ButtonType {
+    type Err = crate::exceptions::APIRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+            .map_err(|_| crate::exceptions::APIRequestError::RequestFailed(format!("Invalid button type: {}", s)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Button {
     pub id: Option<String>,
-    #[serde(rename = "type")]
-    pub type_: Option<String>,
-    #[serde(rename = "button_text")]
+    #[serde(rename = "type", default)]
+    pub type_: ButtonType,
+    #[serde(rename = "button_text", skip_serializing_if = "Option::is_none")]
     pub button_text: Option<String>,
-    #[serde(rename = "button_selection")]
+    #[serde(rename = "button_selection", skip_serializing_if = "Option::is_none")]
     pub button_selection: Option<ButtonSelection>,
-    #[serde(rename = "button_calendar")]
+    #[serde(rename = "button_calendar", skip_serializing_if = "Option::is_none")]
     pub button_calendar: Option<ButtonCalendar>,
-    #[serde(rename = "button_number_picker")]
+    #[serde(rename = "button_number_picker", skip_serializing_if = "Option::is_none")]
     pub button_number_picker: Option<ButtonNumberPicker>,
-    #[serde(rename = "button_string_picker")]
+    #[serde(rename = "button_string_picker", skip_serializing_if = "Option::is_none")]
     pub button_string_picker: Option<ButtonStringPicker>,
-    #[serde(rename = "button_location")]
+    #[serde(rename = "button_location", skip_serializing_if = "Option::is_none")]
     pub button_location: Option<ButtonLocation>,
-    #[serde(rename = "button_textbox")]
+    #[serde(rename = "button_textbox", skip_serializing_if = "Option::is_none")]
     pub button_textbox: Option<ButtonTextbox>,
+    #[serde(rename = "button_payment", skip_serializing_if = "Option::is_none")]
+    pub button_payment: Option<ButtonPayment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Button {
+    pub fn new(id: &str, type_: ButtonType, text: &str) -> Self {
+        Button {
+            id: Some(id.to_string()),
+            type_,
+            button_text: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn simple(id: &str, text: &str) -> Self {
+        Self::new(id, ButtonType::Simple, text)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct KeypadRow {
     pub buttons: Vec<Button>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Keypad {
     pub rows: Vec<KeypadRow>,
-    #[serde(rename = "resize_keyboard")]
-    pub resize_keyboard: bool,
-    #[serde(rename = "on_time_keyboard")]
-    pub on_time_keyboard: bool,
+    #[serde(rename = "resize_keyboard", skip_serializing_if = "Option::is_none")]
+    pub resize_keyboard: Option<bool>,
+    #[serde(rename = "on_time_keyboard", skip_serializing_if = "Option::is_none")]
+    pub on_time_keyboard: Option<bool>,
+}
+
+impl Keypad {
+    pub fn buttons(&self) -> impl Iterator<Item = &Button> {
+        self.rows.iter().flat_map(|row| row.buttons.iter())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -248,6 +357,7 @@ pub struct Message {
     pub message_id: String,
     pub sender_id: String,
     pub text: Option<String>,
+    pub normalized_text: Option<String>,
     pub raw_data: serde_json::Value,
     pub time: Option<String>,
     pub is_edited: bool,
@@ -274,6 +384,11 @@ impl Message {
         raw_data: Option<serde_json::Value>,
     ) -> Self {
         let raw = raw_data.unwrap_or_default();
+        let text = raw.get("text")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .or(text);
+        let normalized_text = text.as_deref().map(|s| bot.normalize_text(s));
         Message {
             bot,
             chat_id,
@@ -285,10 +400,8 @@ impl Message {
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string())
                 .unwrap_or(sender_id),
-            text: raw.get("text")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-                .or(text),
+            text,
+            normalized_text,
             raw_data: raw.clone(),
             time: raw.get("time").and_then(|v| v.as_str()).map(|s| s.to_string()),
             is_edited: raw.get("is_edited").and_then(|v| v.as_bool()).unwrap_or(false),
@@ -320,7 +433,7 @@ impl Message {
         parking_lot::RwLock::new(HashMap::new())
     }
 
-    pub async fn reply(&self, text: &str) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
+    pub async fn reply(&self, text: impl Into<crate::rich_text::MessageText>) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
         self.bot.send_message(
             &self.chat_id,
             text,
@@ -372,8 +485,8 @@ impl Message {
 
     pub async fn reply_keypad(
         &self,
-        text: &str,
-        keypad: &serde_json::Value,
+        text: impl Into<crate::rich_text::MessageText>,
+        keypad: &crate::context::Keypad,
     ) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
         self.bot.send_message(
             &self.chat_id,
@@ -388,8 +501,8 @@ impl Message {
 
     pub async fn reply_inline(
         &self,
-        text: &str,
-        inline_keypad: &serde_json::Value,
+        text: impl Into<crate::rich_text::MessageText>,
+        inline_keypad: &crate::context::Keypad,
     ) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
         self.bot.send_message(
             &self.chat_id,
@@ -402,13 +515,152 @@ impl Message {
         ).await
     }
 
-    pub async fn edit(&self, new_text: &str) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
+    pub async fn edit(&self, new_text: impl Into<crate::rich_text::MessageText>) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
         self.bot.edit_message_text(&self.chat_id, &self.message_id, new_text).await
     }
 
     pub async fn delete(&self) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
         self.bot.delete_message(&self.chat_id, &self.message_id).await
     }
+
+    pub fn file_id(&self) -> Option<&str> {
+        self.file.as_ref()
+            .and_then(|f| f.file_id.as_deref())
+            .or_else(|| self.sticker.as_ref().and_then(|s| s.file.file_id.as_deref()))
+    }
+
+    pub async fn download(&self, path: impl AsRef<std::path::Path>) -> Result<u64, crate::exceptions::APIRequestError> {
+        let file_id = self.file_id().ok_or_else(|| {
+            crate::exceptions::APIRequestError::RequestFailed("Message does not carry a file".to_string())
+        })?;
+        self.bot.download_file(file_id, path).await
+    }
+
+    pub async fn download_bytes(&self) -> Result<Vec<u8>, crate::exceptions::APIRequestError> {
+        let file_id = self.file_id().ok_or_else(|| {
+            crate::exceptions::APIRequestError::RequestFailed("Message does not carry a file".to_string())
+        })?;
+        self.bot.download_to_bytes(file_id).await
+    }
+
+    pub fn delete_after(
+        &self,
+        delay: std::time::Duration,
+    ) -> Result<crate::jobs::JobHandle, crate::exceptions::APIRequestError> {
+        self.bot.delete_message_after(&self.chat_id, &self.message_id, delay)
+    }
+
+    pub fn button_id(&self) -> Option<&str> {
+        self.aux_data.as_ref().and_then(|a| a.button_id.as_deref())
+    }
+
+    pub fn calendar_date(&self, calendar: crate::calendar::CalendarType) -> Option<crate::calendar::CalendarDate> {
+        crate::calendar::CalendarDate::parse(self.normalized_text.as_deref()?, calendar)
+    }
+
+    pub async fn reply_template(
+        &self,
+        templates: &crate::template::Templates,
+        name: &str,
+        context: serde_json::Value,
+    ) -> Result<serde_json::Value, crate::exceptions::APIRequestError> {
+        let locale = self.locale();
+        let mut context = match context {
+            serde_json::Value::Object(map) => map,
+            serde_json::Value::Null => serde_json::Map::new(),
+            other => serde_json::Map::from_iter([("value".to_string(), other)]),
+        };
+        context.entry("chat_id").or_insert_with(|| self.chat_id.clone().into());
+        context.entry("sender_id").or_insert_with(|| self.sender_id.clone().into());
+        context.entry("locale").or_insert_with(|| locale.clone().into());
+        if !context.contains_key("user_name") && templates.uses(name, "user_name") {
+            context.insert("user_name".to_string(), self.bot.get_name(&self.sender_id).await.into());
+        }
+
+        let translate = self.translator();
+        let rendered = templates.render_with(name, &serde_json::Value::Object(context), &locale, &translate)?;
+        rendered.send(&self.bot, &self.chat_id, Some(&self.message_id)).await
+    }
+
+    pub fn locale(&self) -> String {
+        self.bot.user_locale(&self.chat_id, &self.sender_id)
+    }
+
+    pub fn set_locale(&self, locale: &str) {
+        self.bot.set_user_locale(&self.chat_id, &self.sender_id, locale);
+    }
+
+    pub fn t(&self, key: &str, args: &[(&str, &str)]) -> String {
+        self.bot.i18n.translate(&self.locale(), key, args)
+    }
+
+    pub fn tn(&self, key: &str, count: u64, args: &[(&str, &str)]) -> String {
+        self.bot.i18n.plural(&self.locale(), key, count, args)
+    }
+
+    pub fn translator(&self) -> impl Fn(&str) -> Option<String> + Send + Sync + 'static {
+        self.bot.i18n.translator(&self.locale())
+    }
+
+    pub async fn confirm(
+        &self,
+        text: &str,
+        timeout: std::time::Duration,
+    ) -> Result<bool, crate::exceptions::APIRequestError> {
+        self.confirm_with(text, "Yes", "No", timeout).await
+    }
+
+    pub async fn confirm_with(
+        &self,
+        text: &str,
+        yes_label: &str,
+        no_label: &str,
+        timeout: std::time::Duration,
+    ) -> Result<bool, crate::exceptions::APIRequestError> {
+        let token = crate::conversation::unique_token();
+        let yes_id = format!("confirm:{}:yes", token);
+        let no_id = format!("confirm:{}:no", token);
+        let keypad = Keypad {
+            rows: vec![KeypadRow {
+                buttons: vec![Button::simple(&yes_id, yes_label), Button::simple(&no_id, no_label)],
+            }],
+            resize_keyboard: None,
+            on_time_keyboard: None,
+        };
+
+        let response = self.bot.send_message(
+            &self.chat_id,
+            text,
+            None,
+            Some(&keypad),
+            false,
+            Some(&self.message_id),
+            None,
+        ).await?;
+        let prompt_id = crate::send::message_id_of(&response).map(|id| id.to_string());
+
+        let confirmed = self.await_confirmation(&token, timeout).await;
+
+        if let Some(prompt_id) = prompt_id {
+            if let Err(e) = self.bot.delete_message(&self.chat_id, &prompt_id).await {
+                crate::logger::log_error(&format!("Failed to remove confirmation prompt {}: {}", prompt_id, e));
+            }
+        }
+
+        Ok(confirmed)
+    }
+
+    async fn await_confirmation(&self, token: &str, timeout: std::time::Duration) -> bool {
+        let yes_id = format!("confirm:{}:yes", token);
+        let prefix = format!("confirm:{}:", token);
+        let answer = self.bot.wait_for(
+            &self.chat_id,
+            Some(&self.sender_id),
+            Box::new(move |m| m.button_id().is_some_and(|id| id.starts_with(&prefix))),
+            timeout,
+        ).await;
+        answer.is_some_and(|m| m.button_id() == Some(yes_id.as_str()))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -452,3 +704,53 @@ impl InlineMessage {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn click(bot: &Arc<crate::api::Robot>, sender_id: &str, button_id: &str) -> Message {
+        Message::new(bot.clone(), "c".into(), "".into(), sender_id.into(), None, Some(serde_json::json!({
+            "message_id": "5",
+            "aux_data": {"button_id": button_id}
+        })))
+    }
+
+    async fn pending(bot: &Arc<crate::api::Robot>) {
+        while bot.waiters.pending() == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirmation_times_out_as_no() {
+        let bot = Arc::new(crate::api::Robot::new("test-token".to_string(), None, None, None, None, None));
+        let msg = Message::new(bot.clone(), "c".into(), "1".into(), "alice".into(), Some("delete".into()), None);
+        assert!(!msg.await_confirmation("t1", Duration::from_secs(30)).await);
+        assert_eq!(bot.waiters.pending(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirmation_only_accepts_the_asking_user() {
+        let bot = Arc::new(crate::api::Robot::new("test-token".to_string(), None, None, None, None, None));
+        let msg = Message::new(bot.clone(), "c".into(), "1".into(), "alice".into(), Some("delete".into()), None);
+        let waiting = tokio::spawn(async move { msg.await_confirmation("t2", Duration::from_secs(30)).await });
+        pending(&bot).await;
+
+        assert!(!bot.waiters.dispatch(&click(&bot, "mallory", "confirm:t2:yes")));
+        assert!(!bot.waiters.dispatch(&click(&bot, "alice", "confirm:other:yes")));
+        assert!(bot.waiters.dispatch(&click(&bot, "alice", "confirm:t2:yes")));
+        assert!(waiting.await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn confirmation_no_button_returns_false() {
+        let bot = Arc::new(crate::api::Robot::new("test-token".to_string(), None, None, None, None, None));
+        let msg = Message::new(bot.clone(), "c".into(), "1".into(), "alice".into(), Some("delete".into()), None);
+        let waiting = tokio::spawn(async move { msg.await_confirmation("t3", Duration::from_secs(30)).await });
+        pending(&bot).await;
+
+        assert!(bot.waiters.dispatch(&click(&bot, "alice", "confirm:t3:no")));
+        assert!(!waiting.await.unwrap());
+    }
+}
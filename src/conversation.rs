@@ -0,0 +1,86 @@
+use crate::context::Message;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use tokio::time::Duration;
+
+pub type WaitFilter = Box<dyn Fn(&Message) -> bool + Send + Sync>;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn unique_token() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or_default();
+    format!("{:x}{:x}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed), nanos & 0xfffff)
+}
+
+struct Waiter {
+    id: u64,
+    chat_id: String,
+    sender_id: Option<String>,
+    filter: WaitFilter,
+    tx: oneshot::Sender<Message>,
+}
+
+#[derive(Clone, Default)]
+pub struct Waiters {
+    waiters: Arc<Mutex<Vec<Waiter>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Waiters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.waiters.lock().len()
+    }
+
+    pub async fn wait(
+        &self,
+        chat_id: &str,
+        sender_id: Option<&str>,
+        filter: WaitFilter,
+        timeout: Duration,
+    ) -> Option<Message> {
+        let (tx, rx) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.waiters.lock().push(Waiter {
+            id,
+            chat_id: chat_id.to_string(),
+            sender_id: sender_id.map(|s| s.to_string()),
+            filter,
+            tx,
+        });
+
+        let result = tokio::time::timeout(timeout, rx).await;
+        self.waiters.lock().retain(|w| w.id != id);
+        result.ok().and_then(|r| r.ok())
+    }
+
+    pub(crate) fn dispatch(&self, msg: &Message) -> bool {
+        let mut waiters = self.waiters.lock();
+        waiters.retain(|w| !w.tx.is_closed());
+
+        let view = msg.for_filters();
+        let position = waiters.iter().position(|w| {
+            w.chat_id == msg.chat_id
+                && w.sender_id.as_ref().is_none_or(|s| *s == msg.sender_id)
+                && (w.filter)(&view)
+        });
+        match position {
+            Some(index) => waiters.remove(index).tx.send(msg.clone()).is_ok(),
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for Waiters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Waiters")
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
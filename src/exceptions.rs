@@ -13,5 +13,69 @@ pub enum APIRequestError {
     
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
+
+    #[error("Transfer cancelled")]
+    Cancelled,
+
+    #[error("Upload rejected: {0}")]
+    UploadRejected(String),
+
+    #[error("Upload failed: {0}")]
+    UploadFailed(String),
+
+    #[error("Upload URL expired: {0}")]
+    UploadUrlExpired(String),
+
+    #[error("Invalid keypad: {}", crate::validation::describe(.0))]
+    InvalidKeypad(Vec<crate::validation::KeypadIssue>),
+
+    #[error("Invalid callback data: {0}")]
+    InvalidCallbackData(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Invalid template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Form cancelled: {0}")]
+    FormCancelled(String),
+
+    #[error("Too many invalid answers for form field {field}: {error}")]
+    FormAttemptsExceeded { field: String, error: String },
+
+    #[error("Sent {} of {total} chunks: {source}", .sent.len())]
+    PartialSend {
+        sent: Vec<serde_json::Value>,
+        total: usize,
+        source: Box<APIRequestError>,
+    },
+}
+
+impl APIRequestError {
+    pub fn from_upload_status(status: u16, body: &str) -> Self {
+        let detail = format!("HTTP {}: {}", status, body);
+        match status {
+            401 | 403 | 404 | 410 => APIRequestError::UploadUrlExpired(detail),
+            408 | 429 | 500..=599 => APIRequestError::UploadFailed(detail),
+            413 => APIRequestError::UploadRejected(format!("File too large ({})", detail)),
+            415 => APIRequestError::UploadRejected(format!("Unsupported file type ({})", detail)),
+            _ => APIRequestError::UploadRejected(detail),
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        match self {
+            APIRequestError::HttpError(e) => {
+                e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+            }
+            APIRequestError::UploadFailed(_) | APIRequestError::UploadUrlExpired(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        !self.is_transient() && !matches!(self, APIRequestError::Cancelled)
+    }
 }
 
@@ -0,0 +1,173 @@
+use crate::exceptions::APIRequestError;
+use crate::media::{InputFile, MediaKind};
+use parking_lot::{Mutex, RwLock};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::io::AsyncReadExt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheKeyMode {
+    #[default]
+    ContentHash,
+    PathAndMtime,
+}
+
+pub trait FileIdStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<String>;
+    fn put(&self, key: &str, file_id: &str);
+    fn remove(&self, key: &str);
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryFileIdStore {
+    ids: RwLock<HashMap<String, String>>,
+}
+
+impl MemoryFileIdStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileIdStore for MemoryFileIdStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.ids.read().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, file_id: &str) {
+        self.ids.write().insert(key.to_string(), file_id.to_string());
+    }
+
+    fn remove(&self, key: &str) {
+        self.ids.write().remove(key);
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonFileIdStore {
+    path: PathBuf,
+    ids: Mutex<HashMap<String, String>>,
+}
+
+impl JsonFileIdStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, APIRequestError> {
+        let path = path.as_ref().to_path_buf();
+        let ids = match std::fs::read(&path) {
+            Ok(raw) => serde_json::from_slice(&raw)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(APIRequestError::RequestFailed(format!("Failed to read file_id cache: {}", e)));
+            }
+        };
+        Ok(JsonFileIdStore {
+            path,
+            ids: Mutex::new(ids),
+        })
+    }
+
+    fn flush(&self, ids: &HashMap<String, String>) {
+        let result = serde_json::to_vec_pretty(ids)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| {
+                let tmp = self.path.with_extension("tmp");
+                std::fs::write(&tmp, raw)
+                    .and_then(|_| std::fs::rename(&tmp, &self.path))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            crate::logger::log_error(&format!("Failed to write file_id cache: {}", e));
+        }
+    }
+}
+
+impl FileIdStore for JsonFileIdStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.ids.lock().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, file_id: &str) {
+        let mut ids = self.ids.lock();
+        ids.insert(key.to_string(), file_id.to_string());
+        self.flush(&ids);
+    }
+
+    fn remove(&self, key: &str) {
+        let mut ids = self.ids.lock();
+        if ids.remove(key).is_some() {
+            self.flush(&ids);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FileIdCache {
+    store: Arc<dyn FileIdStore>,
+    mode: CacheKeyMode,
+}
+
+impl FileIdCache {
+    pub fn new<S: FileIdStore + 'static>(store: S, mode: CacheKeyMode) -> Self {
+        FileIdCache {
+            store: Arc::new(store),
+            mode,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.store.get(key)
+    }
+
+    pub fn put(&self, key: &str, file_id: &str) {
+        self.store.put(key, file_id);
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.store.remove(key);
+    }
+
+    pub async fn key_for(&self, kind: MediaKind, file: &InputFile) -> Option<String> {
+        let fingerprint = match file {
+            InputFile::Path(path) => match self.mode {
+                CacheKeyMode::ContentHash => hash_file(path).await?,
+                CacheKeyMode::PathAndMtime => {
+                    let meta = tokio::fs::metadata(path).await.ok()?;
+                    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+                    let canonical = tokio::fs::canonicalize(path).await.ok()?;
+                    format!("{}@{}:{}", canonical.display(), mtime, meta.len())
+                }
+            },
+            InputFile::Bytes { data, .. } => to_hex(&Sha256::digest(data)),
+            _ => return None,
+        };
+        Some(format!("{}:{}", kind, fingerprint))
+    }
+}
+
+impl std::fmt::Debug for FileIdCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileIdCache")
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+async fn hash_file(path: &Path) -> Option<String> {
+    let mut file = tokio::fs::File::open(path).await.ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Some(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,487 @@
+use crate::calendar::{CalendarDate, CalendarType};
+use crate::context::{
+    Button, ButtonCalendar, ButtonLocation, ButtonNumberPicker, ButtonStringPicker, ButtonTextbox, ButtonType,
+    Keypad, KeypadRow, Message,
+};
+use crate::exceptions::APIRequestError;
+use crate::layout::interpolate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+pub type FieldValidator = Arc<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum FieldKind {
+    Text {
+        type_line: String,
+        type_keypad: String,
+        place_holder: Option<String>,
+    },
+    Number {
+        min: i64,
+        max: i64,
+        default: Option<i64>,
+    },
+    Date {
+        calendar_type: CalendarType,
+        min_year: Option<i32>,
+        max_year: Option<i32>,
+    },
+    Choice {
+        items: Vec<String>,
+        default: Option<String>,
+    },
+    Location {
+        image_url: String,
+    },
+}
+
+#[derive(Clone)]
+pub struct FormField {
+    pub name: String,
+    pub prompt: String,
+    pub label: String,
+    pub kind: FieldKind,
+    validator: Option<FieldValidator>,
+}
+
+impl FormField {
+    pub fn new(name: &str, prompt: &str, kind: FieldKind) -> Self {
+        FormField {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            label: prompt.to_string(),
+            kind,
+            validator: None,
+        }
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    pub fn validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    fn button(&self, id: &str) -> Button {
+        let mut button = Button::new(id, ButtonType::Simple, &self.label);
+        match self.kind {
+            FieldKind::Text { ref type_line, ref type_keypad, ref place_holder } => {
+                button.type_ = ButtonType::Textbox;
+                button.button_textbox = Some(ButtonTextbox {
+                    type_line: Some(type_line.clone()),
+                    type_keypad: Some(type_keypad.clone()),
+                    place_holder: place_holder.clone(),
+                    title: Some(self.label.clone()),
+                    default_value: None,
+                });
+            }
+            FieldKind::Number { min, max, default } => {
+                button.type_ = ButtonType::NumberPicker;
+                button.button_number_picker = Some(ButtonNumberPicker {
+                    min_value: Some(min.to_string()),
+                    max_value: Some(max.to_string()),
+                    default_value: default.map(|d| d.to_string()),
+                    title: Some(self.label.clone()),
+                });
+            }
+            FieldKind::Date { calendar_type, min_year, max_year } => {
+                button.type_ = ButtonType::Calendar;
+                button.button_calendar = Some(ButtonCalendar {
+                    type_: Some(calendar_type),
+                    min_year: min_year.map(|y| y.to_string()),
+                    max_year: max_year.map(|y| y.to_string()),
+                    title: Some(self.label.clone()),
+                    default_value: None,
+                });
+            }
+            FieldKind::Choice { ref items, ref default } => {
+                button.type_ = ButtonType::StringPicker;
+                button.button_string_picker = Some(ButtonStringPicker {
+                    items: items.clone(),
+                    default_value: default.clone(),
+                    title: Some(self.label.clone()),
+                });
+            }
+            FieldKind::Location { ref image_url } => {
+                button.type_ = ButtonType::Location;
+                button.button_location = Some(ButtonLocation {
+                    type_: Some("Picker".to_string()),
+                    location_image_url: Some(image_url.clone()),
+                    title: Some(self.label.clone()),
+                    ..Default::default()
+                });
+            }
+        }
+        button
+    }
+
+    fn accepts_plain_reply(&self, msg: &Message) -> bool {
+        match self.kind {
+            FieldKind::Location { .. } => msg.location.is_some(),
+            _ => msg.normalized_text.is_some(),
+        }
+    }
+
+    fn parse(&self, msg: &Message) -> Result<Value, String> {
+        let text = msg.normalized_text.as_deref().map(str::trim).unwrap_or_default();
+        let value = match self.kind {
+            FieldKind::Text { .. } => {
+                let raw = msg.text.as_deref().map(str::trim).unwrap_or_default();
+                if raw.is_empty() {
+                    return Err("empty answer".to_string());
+                }
+                json!(raw)
+            }
+            FieldKind::Number { min, max, .. } => {
+                let number: i64 = text.parse().map_err(|_| format!("{:?} is not a number", text))?;
+                if number < min || number > max {
+                    return Err(format!("{} is not between {} and {}", number, min, max));
+                }
+                json!(number)
+            }
+            FieldKind::Date { calendar_type, min_year, max_year } => {
+                let date = CalendarDate::parse(text, calendar_type)
+                    .ok_or_else(|| format!("{:?} is not a date", text))?;
+                if min_year.is_some_and(|y| date.year < y) || max_year.is_some_and(|y| date.year > y) {
+                    return Err(format!("{} is out of range", date));
+                }
+                json!(date)
+            }
+            FieldKind::Choice { ref items, .. } => {
+                if !items.iter().any(|i| i == text) {
+                    return Err(format!("{:?} is not one of the options", text));
+                }
+                json!(text)
+            }
+            FieldKind::Location { .. } => {
+                let (latitude, longitude) = match msg.location {
+                    Some(ref loc) => (
+                        loc.latitude.as_deref().unwrap_or_default().to_string(),
+                        loc.longitude.as_deref().unwrap_or_default().to_string(),
+                    ),
+                    None => match text.split_once(',') {
+                        Some((lat, lon)) => (lat.to_string(), lon.to_string()),
+                        None => return Err("no location received".to_string()),
+                    },
+                };
+                let coordinates = Coordinates {
+                    latitude: latitude.trim().parse().map_err(|_| format!("invalid latitude {:?}", latitude))?,
+                    longitude: longitude.trim().parse().map_err(|_| format!("invalid longitude {:?}", longitude))?,
+                };
+                json!(coordinates)
+            }
+        };
+
+        if let Some(ref validator) = self.validator {
+            validator(&value)?;
+        }
+        Ok(value)
+    }
+}
+
+impl std::fmt::Debug for FormField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FormField")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("has_validator", &self.validator.is_some())
+            .finish()
+    }
+}
+
+enum Review {
+    Accept(Value),
+    Retry(String),
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub struct Form {
+    name: String,
+    fields: Vec<FormField>,
+    timeout: Duration,
+    max_attempts: u32,
+    invalid_message: String,
+    cancel_keywords: Vec<String>,
+    cancel_button: Option<String>,
+    cancel_message: Option<String>,
+}
+
+impl Form {
+    pub fn new(name: &str) -> Self {
+        Form {
+            name: name.to_string(),
+            fields: Vec::new(),
+            timeout: Duration::from_secs(300),
+            max_attempts: 3,
+            invalid_message: "Invalid answer: {{ error }}. Please try again.".to_string(),
+            cancel_keywords: vec!["/cancel".to_string()],
+            cancel_button: None,
+            cancel_message: Some("Form cancelled.".to_string()),
+        }
+    }
+
+    pub fn field(mut self, field: FormField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn text(self, name: &str, prompt: &str) -> Self {
+        self.field(FormField::new(name, prompt, FieldKind::Text {
+            type_line: "SingleLine".to_string(),
+            type_keypad: "String".to_string(),
+            place_holder: None,
+        }))
+    }
+
+    pub fn number(self, name: &str, prompt: &str, min: i64, max: i64) -> Self {
+        self.field(FormField::new(name, prompt, FieldKind::Number { min, max, default: None }))
+    }
+
+    pub fn date(self, name: &str, prompt: &str, calendar_type: CalendarType) -> Self {
+        self.field(FormField::new(name, prompt, FieldKind::Date {
+            calendar_type,
+            min_year: None,
+            max_year: None,
+        }))
+    }
+
+    pub fn choice(self, name: &str, prompt: &str, items: &[&str]) -> Self {
+        self.field(FormField::new(name, prompt, FieldKind::Choice {
+            items: items.iter().map(|i| i.to_string()).collect(),
+            default: None,
+        }))
+    }
+
+    pub fn location(self, name: &str, prompt: &str, image_url: &str) -> Self {
+        self.field(FormField::new(name, prompt, FieldKind::Location {
+            image_url: image_url.to_string(),
+        }))
+    }
+
+    pub fn validate<F>(mut self, name: &str, validator: F) -> Self
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        if let Some(field) = self.fields.iter_mut().find(|f| f.name == name) {
+            field.validator = Some(Arc::new(validator));
+        }
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn invalid_message(mut self, message: &str) -> Self {
+        self.invalid_message = message.to_string();
+        self
+    }
+
+    pub fn cancel_keywords(mut self, keywords: &[&str]) -> Self {
+        self.cancel_keywords = keywords.iter().map(|k| k.to_string()).collect();
+        self
+    }
+
+    pub fn cancel_button(mut self, title: Option<&str>) -> Self {
+        self.cancel_button = title.map(|t| t.to_string());
+        self
+    }
+
+    pub fn cancel_message(mut self, message: Option<&str>) -> Self {
+        self.cancel_message = message.map(|m| m.to_string());
+        self
+    }
+
+    fn is_cancel(&self, msg: &Message, cancel_id: &str) -> bool {
+        if let Some(id) = msg.button_id() {
+            return id == cancel_id;
+        }
+        let text = msg.normalized_text.as_deref().map(str::trim).unwrap_or_default();
+        !text.is_empty() && self.cancel_keywords.iter().any(|k| k == text || msg.bot.normalize_text(k) == text)
+    }
+
+    fn review(&self, field: &FormField, answer: &Message, cancel_id: &str, attempts: u32) -> Result<Review, APIRequestError> {
+        if self.is_cancel(answer, cancel_id) {
+            return Ok(Review::Cancel);
+        }
+        match field.parse(answer) {
+            Ok(value) => Ok(Review::Accept(value)),
+            Err(error) if attempts < self.max_attempts => {
+                Ok(Review::Retry(interpolate(&self.invalid_message, &[("error", &error)])))
+            }
+            Err(error) => Err(APIRequestError::FormAttemptsExceeded {
+                field: field.name.clone(),
+                error,
+            }),
+        }
+    }
+
+    pub async fn collect(&self, msg: &Message) -> Result<Map<String, Value>, APIRequestError> {
+        let token = crate::conversation::unique_token();
+        let cancel_id = format!("form-cancel:{}:{}", self.name, token);
+        let mut answers = Map::new();
+
+        for field in &self.fields {
+            let button_id = format!("form:{}:{}:{}", self.name, token, field.name);
+            let mut rows = vec![KeypadRow { buttons: vec![field.button(&button_id)] }];
+            if let Some(ref title) = self.cancel_button {
+                rows.push(KeypadRow { buttons: vec![Button::new(&cancel_id, ButtonType::Simple, title)] });
+            }
+            let keypad = Keypad {
+                rows,
+                resize_keyboard: None,
+                on_time_keyboard: None,
+            };
+
+            let mut attempts = 0;
+            let value = loop {
+                attempts += 1;
+                msg.bot.send_message(&msg.chat_id, &field.prompt, None, Some(&keypad), false, None, None).await?;
+
+                let expected = button_id.clone();
+                let expected_cancel = cancel_id.clone();
+                let plain_field = field.clone();
+                let form = self.clone();
+                let answer = msg.bot.wait_for(
+                    &msg.chat_id,
+                    Some(&msg.sender_id),
+                    Box::new(move |m| form.is_cancel(m, &expected_cancel) || match m.button_id() {
+                        Some(id) => id == expected,
+                        None => plain_field.accepts_plain_reply(m),
+                    }),
+                    self.timeout,
+                ).await.ok_or_else(|| {
+                    APIRequestError::Timeout(format!("no answer for form field {}", field.name))
+                })?;
+
+                match self.review(field, &answer, &cancel_id, attempts)? {
+                    Review::Accept(value) => break value,
+                    Review::Retry(text) => {
+                        msg.bot.send_message(&msg.chat_id, &text, None, None, false, Some(&answer.message_id), None).await?;
+                    }
+                    Review::Cancel => {
+                        if let Some(ref text) = self.cancel_message {
+                            msg.bot.send_message(&msg.chat_id, text, None, None, false, Some(&answer.message_id), None).await?;
+                        }
+                        return Err(APIRequestError::FormCancelled(self.name.clone()));
+                    }
+                }
+            };
+            answers.insert(field.name.clone(), value);
+        }
+
+        Ok(answers)
+    }
+
+    pub async fn run<T: DeserializeOwned>(&self, msg: &Message) -> Result<T, APIRequestError> {
+        let answers = self.collect(msg).await?;
+        Ok(serde_json::from_value(Value::Object(answers))?)
+    }
+
+    pub fn start<T, F, Fut>(&self, msg: Message, handler: F)
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: FnOnce(Message, T) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let form = self.clone();
+        tokio::spawn(async move {
+            match form.run::<T>(&msg).await {
+                Ok(result) => handler(msg, result).await,
+                Err(APIRequestError::FormCancelled(_)) => {
+                    crate::logger::log_info(&format!("Form {} was cancelled by {}", form.name, msg.sender_id));
+                }
+                Err(e) => crate::logger::log_error(&format!("Form {} was not completed: {}", form.name, e)),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Robot;
+
+    const CANCEL_ID: &str = "form-cancel:signup:t";
+
+    fn reply(text: &str) -> Message {
+        let bot = Arc::new(Robot::new("test-token".to_string(), None, None, None, None, None));
+        Message::new(bot, "c".into(), "9".into(), "u".into(), Some(text.to_string()), None)
+    }
+
+    fn click(button_id: &str) -> Message {
+        let bot = Arc::new(Robot::new("test-token".to_string(), None, None, None, None, None));
+        Message::new(bot, "c".into(), "9".into(), "u".into(), None, Some(json!({"aux_data": {"button_id": button_id}})))
+    }
+
+    fn form() -> Form {
+        Form::new("signup").number("age", "Age?", 1, 120).max_attempts(2)
+    }
+
+    #[test]
+    fn re_asks_with_the_error_until_the_attempts_run_out() {
+        let form = form().invalid_message("نامعتبر: {{ error }}");
+        let field = &form.fields[0];
+
+        match form.review(field, &reply("abc"), CANCEL_ID, 1).unwrap() {
+            Review::Retry(text) => assert_eq!(text, "نامعتبر: \"abc\" is not a number"),
+            _ => panic!("expected a retry"),
+        }
+        assert!(matches!(
+            form.review(field, &reply("500"), CANCEL_ID, 2),
+            Err(APIRequestError::FormAttemptsExceeded { ref field, .. }) if field == "age"
+        ));
+        assert!(matches!(form.review(field, &reply("42"), CANCEL_ID, 2), Ok(Review::Accept(v)) if v == json!(42)));
+    }
+
+    #[test]
+    fn cancels_on_keyword_or_button() {
+        let form = form().cancel_keywords(&["/cancel", "لغو"]);
+        let field = &form.fields[0];
+
+        assert!(matches!(form.review(field, &reply(" لغو "), CANCEL_ID, 1), Ok(Review::Cancel)));
+        assert!(matches!(form.review(field, &reply("/cancel"), CANCEL_ID, 1), Ok(Review::Cancel)));
+        assert!(matches!(form.review(field, &click(CANCEL_ID), CANCEL_ID, 1), Ok(Review::Cancel)));
+        assert!(!matches!(form.review(field, &click("form-cancel:other:t"), CANCEL_ID, 1), Ok(Review::Cancel)));
+        assert!(!matches!(form.review(field, &reply("cancel"), CANCEL_ID, 1), Ok(Review::Cancel)));
+    }
+
+    #[test]
+    fn runs_field_validators() {
+        let form = Form::new("signup")
+            .choice("plan", "Plan?", &["basic", "pro"])
+            .validate("plan", |v| match v == "pro" {
+                true => Err("pro is sold out".to_string()),
+                false => Ok(()),
+            });
+        let field = &form.fields[0];
+
+        assert!(matches!(form.review(field, &reply("basic"), CANCEL_ID, 1), Ok(Review::Accept(_))));
+        assert!(matches!(form.review(field, &reply("pro"), CANCEL_ID, 1), Ok(Review::Retry(t)) if t.contains("sold out")));
+        assert!(matches!(form.review(field, &reply("gold"), CANCEL_ID, 1), Ok(Review::Retry(_))));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::api::Robot;
+use crate::context::KeypadRow;
+use crate::exceptions::APIRequestError;
+use crate::layout::interpolate;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const LOCALE_KEY: &str = "locale";
+
+pub type Translator = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+
+    pub fn for_count(locale: &str, count: u64) -> Self {
+        match base_language(locale) {
+            "fa" => match count {
+                0 | 1 => PluralCategory::One,
+                _ => PluralCategory::Other,
+            },
+            "ar" => match (count, count % 100) {
+                (0, _) => PluralCategory::Zero,
+                (1, _) => PluralCategory::One,
+                (2, _) => PluralCategory::Two,
+                (_, 3..=10) => PluralCategory::Few,
+                (_, 11..=99) => PluralCategory::Many,
+                _ => PluralCategory::Other,
+            },
+            _ => match count {
+                1 => PluralCategory::One,
+                _ => PluralCategory::Other,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    Text(String),
+    Plural(HashMap<PluralCategory, String>),
+}
+
+type Catalog = HashMap<String, Entry>;
+
+#[derive(Clone)]
+pub struct I18n {
+    catalogs: Arc<RwLock<HashMap<String, Catalog>>>,
+    default_locale: Arc<RwLock<String>>,
+}
+
+impl I18n {
+    pub fn new(default_locale: &str) -> Self {
+        I18n {
+            catalogs: Arc::new(RwLock::new(HashMap::new())),
+            default_locale: Arc::new(RwLock::new(default_locale.to_string())),
+        }
+    }
+
+    pub fn default_locale(&self) -> String {
+        self.default_locale.read().clone()
+    }
+
+    pub fn set_default_locale(&self, locale: &str) {
+        *self.default_locale.write() = locale.to_string();
+    }
+
+    pub fn locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self.catalogs.read().keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    pub fn has_locale(&self, locale: &str) -> bool {
+        self.catalogs.read().contains_key(locale)
+    }
+
+    pub fn add_catalog(&self, locale: &str, content: &str) -> Result<(), APIRequestError> {
+        let value: Value = serde_json::from_str(content)?;
+        let mut catalog = Catalog::new();
+        flatten(&value, "", &mut catalog)
+            .map_err(|key| APIRequestError::RequestFailed(format!("Invalid catalog entry {} in {}", key, locale)))?;
+        self.catalogs.write().entry(locale.to_string()).or_default().extend(catalog);
+        Ok(())
+    }
+
+    pub fn load_file(&self, locale: &str, path: impl AsRef<Path>) -> Result<(), APIRequestError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+        self.add_catalog(locale, &content)
+    }
+
+    pub fn load_dir(&self, dir: impl AsRef<Path>) -> Result<Vec<String>, APIRequestError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read {}: {}", dir.display(), e)))?;
+        let mut loaded = Vec::new();
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(locale) = path.file_stem().and_then(|s| s.to_str()) {
+                self.load_file(locale, &path)?;
+                loaded.push(locale.to_string());
+            }
+        }
+        loaded.sort();
+        Ok(loaded)
+    }
+
+    pub fn lookup(&self, locale: &str, key: &str) -> Option<String> {
+        self.find(locale, key, |entry| match entry {
+            Entry::Text(text) => Some(text.clone()),
+            Entry::Plural(forms) => forms.get(&PluralCategory::Other).cloned(),
+        })
+    }
+
+    pub fn translate(&self, locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+        match self.lookup(locale, key) {
+            Some(text) => interpolate(&text, args),
+            None => key.to_string(),
+        }
+    }
+
+    pub fn plural(&self, locale: &str, key: &str, count: u64, args: &[(&str, &str)]) -> String {
+        let count_text = count.to_string();
+        let mut args = args.to_vec();
+        args.push(("count", &count_text));
+
+        let text = self.find(locale, key, |entry| match entry {
+            Entry::Text(text) => Some(text.clone()),
+            Entry::Plural(forms) => {
+                let category = PluralCategory::for_count(locale, count);
+                let exact = (count == 0).then(|| forms.get(&PluralCategory::Zero)).flatten();
+                exact.or_else(|| forms.get(&category))
+                    .or_else(|| forms.get(&PluralCategory::Other))
+                    .cloned()
+            }
+        });
+        match text {
+            Some(text) => interpolate(&text, &args),
+            None => key.to_string(),
+        }
+    }
+
+    pub fn translator(&self, locale: &str) -> impl Fn(&str) -> Option<String> + Send + Sync + 'static {
+        let i18n = self.clone();
+        let locale = locale.to_string();
+        move |key| i18n.lookup(&locale, key)
+    }
+
+    fn find<F>(&self, locale: &str, key: &str, pick: F) -> Option<String>
+    where
+        F: Fn(&Entry) -> Option<String>,
+    {
+        let catalogs = self.catalogs.read();
+        let default = self.default_locale.read();
+        [locale, base_language(locale), default.as_str(), base_language(&default)]
+            .iter()
+            .filter_map(|l| catalogs.get(*l)?.get(key))
+            .find_map(pick)
+    }
+}
+
+impl Default for I18n {
+    fn default() -> Self {
+        Self::new("en")
+    }
+}
+
+impl std::fmt::Debug for I18n {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("I18n")
+            .field("default_locale", &self.default_locale())
+            .field("locales", &self.locales())
+            .finish()
+    }
+}
+
+impl Robot {
+    pub fn user_locale(&self, chat_id: &str, sender_id: &str) -> String {
+        self.sessions.read()
+            .get(chat_id)
+            .and_then(|s| s.get(&locale_key(sender_id)))
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_else(|| self.i18n.default_locale())
+    }
+
+    pub fn set_user_locale(&self, chat_id: &str, sender_id: &str, locale: &str) {
+        self.sessions.write()
+            .entry(chat_id.to_string())
+            .or_default()
+            .insert(locale_key(sender_id), Value::String(locale.to_string()));
+    }
+}
+
+pub(crate) fn translate_keypad(rows: &mut [KeypadRow], translator: &Translator) {
+    let translate = |text: &mut Option<String>| {
+        if let Some(translated) = text.as_deref().and_then(translator) {
+            *text = Some(translated);
+        }
+    };
+    for button in rows.iter_mut().flat_map(|row| row.buttons.iter_mut()) {
+        translate(&mut button.button_text);
+        if let Some(ref mut calendar) = button.button_calendar {
+            translate(&mut calendar.title);
+        }
+        if let Some(ref mut picker) = button.button_number_picker {
+            translate(&mut picker.title);
+        }
+        if let Some(ref mut picker) = button.button_string_picker {
+            translate(&mut picker.title);
+        }
+        if let Some(ref mut location) = button.button_location {
+            translate(&mut location.title);
+        }
+        if let Some(ref mut textbox) = button.button_textbox {
+            translate(&mut textbox.title);
+            translate(&mut textbox.place_holder);
+        }
+        if let Some(ref mut payment) = button.button_payment {
+            translate(&mut payment.title);
+            translate(&mut payment.description);
+        }
+    }
+}
+
+fn locale_key(sender_id: &str) -> String {
+    format!("{}:{}", LOCALE_KEY, sender_id)
+}
+
+fn base_language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+fn flatten(value: &Value, prefix: &str, catalog: &mut Catalog) -> Result<(), String> {
+    let Value::Object(map) = value else {
+        return Err(prefix.to_string());
+    };
+    for (name, value) in map {
+        let key = match prefix {
+            "" => name.clone(),
+            _ => format!("{}.{}", prefix, name),
+        };
+        match value {
+            Value::String(text) => {
+                catalog.insert(key, Entry::Text(text.clone()));
+            }
+            Value::Object(_) => match serde_json::from_value::<HashMap<PluralCategory, String>>(value.clone()) {
+                Ok(forms) if forms.contains_key(&PluralCategory::Other) => {
+                    catalog.insert(key, Entry::Plural(forms));
+                }
+                _ => flatten(value, &key, catalog)?,
+            },
+            _ => return Err(key),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_plural_categories_per_language() {
+        assert_eq!(PluralCategory::for_count("fa", 0), PluralCategory::One);
+        assert_eq!(PluralCategory::for_count("fa-IR", 1), PluralCategory::One);
+        assert_eq!(PluralCategory::for_count("fa", 2), PluralCategory::Other);
+        assert_eq!(PluralCategory::for_count("en", 0), PluralCategory::Other);
+        assert_eq!(PluralCategory::for_count("en_US", 1), PluralCategory::One);
+        assert_eq!(PluralCategory::for_count("ar", 0), PluralCategory::Zero);
+        assert_eq!(PluralCategory::for_count("ar", 2), PluralCategory::Two);
+        assert_eq!(PluralCategory::for_count("ar", 103), PluralCategory::Few);
+        assert_eq!(PluralCategory::for_count("ar", 11), PluralCategory::Many);
+        assert_eq!(PluralCategory::for_count("ar", 100), PluralCategory::Other);
+    }
+
+    #[test]
+    fn renders_plural_forms_with_count() {
+        let i18n = I18n::new("en");
+        i18n.add_catalog("en", r#"{"cart": {"items": {"zero": "Empty", "one": "{{ count }} item", "other": "{{ count }} items"}}}"#).unwrap();
+        i18n.add_catalog("fa", r#"{"cart": {"items": {"other": "{{ count }} مورد"}}}"#).unwrap();
+        assert_eq!(i18n.plural("en", "cart.items", 0, &[]), "Empty");
+        assert_eq!(i18n.plural("en", "cart.items", 1, &[]), "1 item");
+        assert_eq!(i18n.plural("en", "cart.items", 5, &[]), "5 items");
+        assert_eq!(i18n.plural("fa", "cart.items", 1, &[]), "1 مورد");
+    }
+
+    #[test]
+    fn treats_objects_without_other_as_sections() {
+        let i18n = I18n::new("en");
+        i18n.add_catalog("en", r#"{"steps": {"one": "First", "two": "Second"}, "empty": {}}"#).unwrap();
+        assert_eq!(i18n.translate("en", "steps.one", &[]), "First");
+        assert_eq!(i18n.plural("en", "steps", 1, &[]), "steps");
+    }
+
+    #[test]
+    fn falls_back_to_base_and_default_locale() {
+        let i18n = I18n::new("en");
+        i18n.add_catalog("en", r#"{"hello": "Hello {{ name }}", "bye": "Bye"}"#).unwrap();
+        i18n.add_catalog("fa", r#"{"hello": "سلام {{ name }}"}"#).unwrap();
+        assert_eq!(i18n.translate("fa-IR", "hello", &[("name", "علی")]), "سلام علی");
+        assert_eq!(i18n.translate("fa", "bye", &[]), "Bye");
+        assert_eq!(i18n.translate("fa", "missing", &[]), "missing");
+        i18n.add_catalog("en", r#"{"json": "{name} stays, {{name}} is replaced"}"#).unwrap();
+        assert_eq!(i18n.translate("en", "json", &[("name", "x")]), "{name} stays, x is replaced");
+    }
+}
@@ -0,0 +1,199 @@
+use crate::exceptions::APIRequestError;
+use crate::jobs::Trigger;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum MisfirePolicy {
+    #[default]
+    RunImmediately,
+    Skip,
+    Grace(Duration),
+}
+
+impl MisfirePolicy {
+    pub fn resolve(&self, job: &StoredJob, now: SystemTime) -> Option<SystemTime> {
+        let scheduled = job.next_run?;
+        let late_by = match now.duration_since(scheduled) {
+            Ok(late_by) if !late_by.is_zero() => late_by,
+            _ => return Some(scheduled),
+        };
+
+        let run_now = match self {
+            MisfirePolicy::RunImmediately => true,
+            MisfirePolicy::Skip => false,
+            MisfirePolicy::Grace(grace) => late_by <= *grace,
+        };
+
+        if run_now {
+            Some(now)
+        } else {
+            match &job.trigger {
+                Trigger::At(_) => None,
+                Trigger::Interval(interval) if !interval.is_zero() => {
+                    let until_next = interval.as_nanos() - late_by.as_nanos() % interval.as_nanos();
+                    let until_next = Duration::new(
+                        (until_next / 1_000_000_000) as u64,
+                        (until_next % 1_000_000_000) as u32,
+                    );
+                    now.checked_add(until_next)
+                }
+                trigger => trigger.next_run(None, now),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredJob {
+    pub key: String,
+    pub kind: String,
+    pub payload: Value,
+    pub trigger: Trigger,
+    pub next_run: Option<SystemTime>,
+    #[serde(default)]
+    pub misfire: MisfirePolicy,
+}
+
+pub trait JobStore: Send + Sync {
+    fn load(&self) -> Result<Vec<StoredJob>, APIRequestError>;
+    fn save(&self, job: &StoredJob) -> Result<(), APIRequestError>;
+    fn remove(&self, key: &str) -> Result<(), APIRequestError>;
+}
+
+pub struct JsonFileJobStore {
+    path: PathBuf,
+    jobs: Mutex<HashMap<String, StoredJob>>,
+}
+
+impl JsonFileJobStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, APIRequestError> {
+        let path = path.as_ref().to_path_buf();
+        let jobs = match std::fs::read(&path) {
+            Ok(raw) => {
+                let list: Vec<Value> = serde_json::from_slice(&raw)?;
+                list.into_iter()
+                    .filter_map(|entry| match serde_json::from_value::<StoredJob>(entry) {
+                        Ok(job) => Some((job.key.clone(), job)),
+                        Err(e) => {
+                            crate::logger::log_error(&format!("Skipping corrupt stored job: {}", e));
+                            None
+                        }
+                    })
+                    .collect()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                return Err(APIRequestError::RequestFailed(format!("Failed to read job store: {}", e)));
+            }
+        };
+        Ok(JsonFileJobStore {
+            path,
+            jobs: Mutex::new(jobs),
+        })
+    }
+
+    fn flush(&self, jobs: &HashMap<String, StoredJob>) -> Result<(), APIRequestError> {
+        let mut list: Vec<&StoredJob> = jobs.values().collect();
+        list.sort_by(|a, b| a.key.cmp(&b.key));
+        let raw = serde_json::to_vec_pretty(&list)?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, raw)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to write job store: {}", e)))
+    }
+}
+
+impl JobStore for JsonFileJobStore {
+    fn load(&self) -> Result<Vec<StoredJob>, APIRequestError> {
+        Ok(self.jobs.lock().values().cloned().collect())
+    }
+
+    fn save(&self, job: &StoredJob) -> Result<(), APIRequestError> {
+        let mut jobs = self.jobs.lock();
+        jobs.insert(job.key.clone(), job.clone());
+        self.flush(&jobs)
+    }
+
+    fn remove(&self, key: &str) -> Result<(), APIRequestError> {
+        let mut jobs = self.jobs.lock();
+        if jobs.remove(key).is_some() {
+            self.flush(&jobs)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn job(trigger: Trigger, next_run: SystemTime) -> StoredJob {
+        StoredJob {
+            key: "job".to_string(),
+            kind: "kind".to_string(),
+            payload: Value::Null,
+            trigger,
+            next_run: Some(next_run),
+            misfire: MisfirePolicy::default(),
+        }
+    }
+
+    #[test]
+    fn keeps_jobs_that_are_not_late() {
+        let scheduled = UNIX_EPOCH + Duration::from_secs(1_000);
+        let job = job(Trigger::At(scheduled), scheduled);
+        assert_eq!(MisfirePolicy::Skip.resolve(&job, scheduled - Duration::from_secs(5)), Some(scheduled));
+    }
+
+    #[test]
+    fn runs_late_jobs_within_policy() {
+        let scheduled = UNIX_EPOCH + Duration::from_secs(1_000);
+        let now = scheduled + Duration::from_secs(30);
+        let job = job(Trigger::At(scheduled), scheduled);
+        assert_eq!(MisfirePolicy::RunImmediately.resolve(&job, now), Some(now));
+        assert_eq!(MisfirePolicy::Grace(Duration::from_secs(60)).resolve(&job, now), Some(now));
+        assert_eq!(MisfirePolicy::Grace(Duration::from_secs(10)).resolve(&job, now), None);
+        assert_eq!(MisfirePolicy::Skip.resolve(&job, now), None);
+    }
+
+    #[test]
+    fn skips_to_the_next_interval_slot() {
+        let scheduled = UNIX_EPOCH + Duration::from_secs(1_000);
+        let job = job(Trigger::Interval(Duration::from_secs(60)), scheduled);
+        let late = MisfirePolicy::Skip.resolve(&job, scheduled + Duration::from_secs(150));
+        assert_eq!(late, Some(scheduled + Duration::from_secs(180)));
+        let on_slot = MisfirePolicy::Skip.resolve(&job, scheduled + Duration::from_secs(120));
+        assert_eq!(on_slot, Some(scheduled + Duration::from_secs(180)));
+    }
+
+    #[test]
+    fn skips_corrupt_entries_when_opening() {
+        let path = std::env::temp_dir().join(format!("job-store-{}.json", std::process::id()));
+        let good = serde_json::to_value(job(Trigger::At(UNIX_EPOCH), UNIX_EPOCH)).unwrap();
+        std::fs::write(&path, serde_json::to_vec(&vec![good, serde_json::json!({"key": "broken"})]).unwrap()).unwrap();
+
+        let store = JsonFileJobStore::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let jobs = store.load().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].key, "job");
+    }
+
+    #[test]
+    fn handles_huge_intervals_without_overflow() {
+        let scheduled = UNIX_EPOCH + Duration::from_secs(1_000);
+        let now = scheduled + Duration::from_secs(10);
+        let job = job(Trigger::Interval(Duration::MAX), scheduled);
+        assert_eq!(MisfirePolicy::Skip.resolve(&job, now), now.checked_add(Duration::MAX - Duration::from_secs(10)));
+        let job = StoredJob { trigger: Trigger::Interval(Duration::from_nanos(1)), ..job };
+        let far = scheduled + Duration::from_secs(u32::MAX as u64 * 4);
+        assert_eq!(MisfirePolicy::Skip.resolve(&job, far), Some(far + Duration::from_nanos(1)));
+    }
+}
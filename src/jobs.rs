@@ -1,21 +1,664 @@
-use std::time::Duration;
-use tokio::time::sleep;
+use crate::api::Robot;
+use crate::calendar::{CalendarDate, CalendarType, TEHRAN_UTC_OFFSET};
+use crate::exceptions::APIRequestError;
+use crate::job_store::{JobStore, MisfirePolicy, StoredJob};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 
-pub struct Job {
-    _delay: u64,
+pub type JobFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub type JobCallback = Arc<dyn Fn(Arc<Robot>) -> JobFuture + Send + Sync>;
+pub type JobKindCallback = Arc<dyn Fn(Arc<Robot>, Value) -> JobFuture + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Trigger {
+    At(SystemTime),
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Trigger {
+    pub fn after(delay: Duration) -> Self {
+        Trigger::At(SystemTime::now() + delay)
+    }
+
+    pub fn cron(expr: &str) -> Result<Self, APIRequestError> {
+        Ok(Trigger::Cron(expr.parse()?))
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        !matches!(self, Trigger::At(_))
+    }
+
+    pub fn next_run(&self, previous: Option<SystemTime>, now: SystemTime) -> Option<SystemTime> {
+        match self {
+            Trigger::At(at) => match previous {
+                None => Some(*at),
+                Some(_) => None,
+            },
+            Trigger::Interval(interval) => {
+                let next = previous.map(|p| p + *interval).unwrap_or(now + *interval);
+                Some(next.max(now))
+            }
+            Trigger::Cron(schedule) => schedule.next_after(now),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CronSpec", into = "CronSpec")]
+pub struct CronSchedule {
+    source: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+    utc_offset: i64,
+    calendar: CalendarType,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, APIRequestError> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(APIRequestError::RequestFailed(
+                format!("Invalid cron expression '{}': expected 5 fields", expr)
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, &WEEKDAY_NAMES)?;
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(CronSchedule {
+            source: expr.trim().to_string(),
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days_of_month: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTH_NAMES)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*" || fields[2] == "?",
+            any_day_of_week: fields[4] == "*" || fields[4] == "?",
+            utc_offset: 0,
+            calendar: CalendarType::Gregorian,
+        })
+    }
+
+    pub fn with_utc_offset(mut self, offset_seconds: i64) -> Self {
+        self.utc_offset = offset_seconds;
+        self
+    }
+
+    pub fn with_calendar(mut self, calendar: CalendarType) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    pub fn in_tehran(self) -> Self {
+        self.with_utc_offset(TEHRAN_UTC_OFFSET)
+    }
+
+    pub fn jalali(self) -> Self {
+        self.with_calendar(CalendarType::Persian).in_tehran()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let secs = after.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        let local = secs + self.utc_offset;
+        let start = local - local.rem_euclid(60) + 60;
+
+        let first_day = start.div_euclid(86_400);
+        let mut minute_of_day = start.rem_euclid(86_400) / 60;
+
+        for day in first_day..first_day + 366 * 8 {
+            let date = CalendarDate::from_days(day, self.calendar);
+            let (month, dom) = (date.month, date.day);
+            let dow = (day + 4).rem_euclid(7) as usize;
+
+            if self.months[month as usize] && self.day_matches(dom as usize, dow) {
+                for m in minute_of_day..1440 {
+                    if self.hours[(m / 60) as usize] && self.minutes[(m % 60) as usize] {
+                        let local_ts = day * 86_400 + m * 60;
+                        let ts = local_ts - self.utc_offset;
+                        return Some(UNIX_EPOCH + Duration::from_secs(ts.max(0) as u64));
+                    }
+                }
+            }
+
+            minute_of_day = 0;
+        }
+
+        None
+    }
+
+    fn day_matches(&self, dom: usize, dow: usize) -> bool {
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (false, true) => self.days_of_month[dom],
+            (true, false) => self.days_of_week[dow],
+            (false, false) => self.days_of_month[dom] || self.days_of_week[dow],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CronSpec {
+    expr: String,
+    #[serde(default)]
+    utc_offset: i64,
+    #[serde(default)]
+    calendar: CalendarType,
+}
+
+impl TryFrom<CronSpec> for CronSchedule {
+    type Error = APIRequestError;
+
+    fn try_from(spec: CronSpec) -> Result<Self, Self::Error> {
+        Ok(CronSchedule::parse(&spec.expr)?
+            .with_utc_offset(spec.utc_offset)
+            .with_calendar(spec.calendar))
+    }
+}
+
+impl From<CronSchedule> for CronSpec {
+    fn from(schedule: CronSchedule) -> Self {
+        CronSpec {
+            expr: schedule.source,
+            utc_offset: schedule.utc_offset,
+            calendar: schedule.calendar,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = APIRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CronSchedule::parse(s)
+    }
+}
+
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<Vec<bool>, APIRequestError> {
+    let invalid = || APIRequestError::RequestFailed(format!("Invalid cron field '{}'", field));
+    let value = |s: &str| -> Result<u32, APIRequestError> {
+        let lower = s.to_ascii_lowercase();
+        if let Some(pos) = names.iter().position(|n| *n == lower) {
+            let offset = if names.len() == 12 { 1 } else { 0 };
+            return Ok(pos as u32 + offset);
+        }
+        let v: u32 = s.parse().map_err(|_| invalid())?;
+        if v < min || v > max {
+            return Err(invalid());
+        }
+        Ok(v)
+    };
+
+    let mut set = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (value(a)?, value(b)?)
+        } else {
+            let v = value(range)?;
+            (v, if part.contains('/') { max } else { v })
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for v in (start..=end).step_by(step as usize) {
+            set[v as usize] = true;
+        }
+    }
+    Ok(set)
+}
+
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: u64,
+    pub name: Option<String>,
+    pub kind: Option<String>,
+    pub trigger: Trigger,
+    pub next_run: Option<SystemTime>,
+    pub runs: u64,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    token: CancellationToken,
+    stored: Option<StoredJob>,
+}
+
+#[derive(Clone)]
+pub struct JobHandle {
+    id: u64,
+    scheduler: Scheduler,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn cancel(&self) {
+        self.scheduler.cancel_id(self.id);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.scheduler.jobs.read().contains_key(&self.id)
+    }
+
+    pub fn info(&self) -> Option<JobInfo> {
+        self.scheduler.jobs.read().get(&self.id).map(|e| e.info.clone())
+    }
+}
+
+impl std::fmt::Debug for JobHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobHandle")
+            .field("id", &self.id)
+            .field("active", &self.is_active())
+            .finish()
+    }
 }
 
-impl Job {
-    pub fn new(delay_seconds: u64, callback: Box<dyn Fn() + Send + Sync>) -> Self {
-        let delay = delay_seconds;
-        let job = Job { _delay: delay };
-        
-        tokio::spawn(async move {
-            sleep(Duration::from_secs(delay)).await;
-            callback();
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    jobs: Arc<RwLock<HashMap<u64, JobEntry>>>,
+    kinds: Arc<RwLock<HashMap<String, JobKindCallback>>>,
+    store: Arc<RwLock<Option<Arc<dyn JobStore>>>>,
+    next_id: Arc<AtomicU64>,
+    tracker: TaskTracker,
+    shutdown: Arc<RwLock<CancellationToken>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schedule<F, Fut>(&self, bot: Arc<Robot>, name: Option<&str>, trigger: Trigger, callback: F) -> JobHandle
+    where
+        F: Fn(Arc<Robot>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: JobCallback = Arc::new(move |bot| Box::pin(callback(bot)));
+        self.schedule_boxed(bot, name, trigger, callback)
+    }
+
+    pub fn schedule_boxed(&self, bot: Arc<Robot>, name: Option<&str>, trigger: Trigger, callback: JobCallback) -> JobHandle {
+        let first_run = trigger.next_run(None, SystemTime::now());
+        self.spawn(bot, name, trigger, first_run, callback, None)
+    }
+
+    pub fn register_kind<F, Fut>(&self, kind: &str, callback: F)
+    where
+        F: Fn(Arc<Robot>, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: JobKindCallback = Arc::new(move |bot, payload| Box::pin(callback(bot, payload)));
+        self.kinds.write().insert(kind.to_string(), callback);
+    }
+
+    pub fn set_store<S: JobStore + 'static>(&self, store: S) {
+        *self.store.write() = Some(Arc::new(store));
+    }
+
+    pub fn has_store(&self) -> bool {
+        self.store.read().is_some()
+    }
+
+    pub fn schedule_persistent(
+        &self,
+        bot: Arc<Robot>,
+        kind: &str,
+        name: Option<&str>,
+        payload: Value,
+        trigger: Trigger,
+        misfire: MisfirePolicy,
+    ) -> Result<JobHandle, APIRequestError> {
+        let store = self.store.read().clone().ok_or_else(|| {
+            APIRequestError::RequestFailed("No job store configured".to_string())
+        })?;
+        let key = match name {
+            Some(n) => n.to_string(),
+            None => format!(
+                "{}-{}-{}",
+                kind,
+                SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis(),
+                self.next_id.fetch_add(1, Ordering::SeqCst)
+            ),
+        };
+        let job = StoredJob {
+            key,
+            kind: kind.to_string(),
+            payload,
+            next_run: trigger.next_run(None, SystemTime::now()),
+            trigger,
+            misfire,
+        };
+        store.save(&job)?;
+        self.spawn_stored(bot, job)
+    }
+
+    pub async fn restore(&self, bot: Arc<Robot>) -> Result<usize, APIRequestError> {
+        let Some(store) = self.store.read().clone() else {
+            return Ok(0);
+        };
+
+        let active: Vec<String> = self.jobs.read().values()
+            .filter_map(|e| e.stored.as_ref().map(|s| s.key.clone()))
+            .collect();
+        let loader = store.clone();
+        let stored = tokio::task::spawn_blocking(move || loader.load())
+            .await
+            .map_err(|e| APIRequestError::RequestFailed(format!("Failed to load stored jobs: {}", e)))??;
+        let now = SystemTime::now();
+        let mut restored = 0;
+
+        for mut job in stored {
+            if active.contains(&job.key) {
+                continue;
+            }
+            match job.misfire.resolve(&job, now) {
+                Some(_) if !self.kinds.read().contains_key(&job.kind) => {
+                    crate::logger::log_error(&format!(
+                        "Skipping stored job {}: unknown job kind '{}'",
+                        job.key, job.kind
+                    ));
+                }
+                Some(next_run) => {
+                    job.next_run = Some(next_run);
+                    persist(store.clone(), job.key.clone(), Some(job.clone())).await;
+                    match self.spawn_stored(bot.clone(), job) {
+                        Ok(_) => restored += 1,
+                        Err(e) => crate::logger::log_error(&format!("Skipping stored job: {}", e)),
+                    }
+                }
+                None => {
+                    crate::logger::log_info(&format!("Dropping missed job {}", job.key));
+                    persist(store.clone(), job.key.clone(), None).await;
+                }
+            }
+        }
+
+        crate::logger::log_info(&format!("Restored {} scheduled jobs", restored));
+        Ok(restored)
+    }
+
+    fn spawn_stored(&self, bot: Arc<Robot>, job: StoredJob) -> Result<JobHandle, APIRequestError> {
+        let kind_callback = self.kinds.read().get(&job.kind).cloned().ok_or_else(|| {
+            APIRequestError::RequestFailed(format!("Unknown job kind '{}'", job.kind))
+        })?;
+        let payload = job.payload.clone();
+        let callback: JobCallback = Arc::new(move |bot| kind_callback(bot, payload.clone()));
+        let key = job.key.clone();
+        Ok(self.spawn(bot, Some(&key), job.trigger.clone(), job.next_run, callback, Some(job)))
+    }
+
+    fn spawn(
+        &self,
+        bot: Arc<Robot>,
+        name: Option<&str>,
+        trigger: Trigger,
+        first_run: Option<SystemTime>,
+        callback: JobCallback,
+        stored: Option<StoredJob>,
+    ) -> JobHandle {
+        if let Some(n) = name {
+            self.cancel_running(n);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let token = self.shutdown.read().child_token();
+
+        self.jobs.write().insert(id, JobEntry {
+            info: JobInfo {
+                id,
+                name: name.map(|n| n.to_string()),
+                kind: stored.as_ref().map(|s| s.kind.clone()),
+                trigger: trigger.clone(),
+                next_run: first_run,
+                runs: 0,
+            },
+            token: token.clone(),
+            stored,
         });
-        
-        job
+
+        let scheduler = self.clone();
+        self.tracker.spawn(async move {
+            let mut next = first_run;
+            while let Some(run_at) = next {
+                let delay = run_at.duration_since(SystemTime::now()).unwrap_or_default();
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = sleep(delay) => {}
+                }
+
+                let run = tokio::spawn(callback(bot.clone()));
+                if let Err(e) = run.await {
+                    crate::logger::log_error(&format!("Scheduled job {} failed: {}", id, e));
+                }
+
+                next = trigger.next_run(Some(run_at), SystemTime::now());
+                if !scheduler.record_run(id, next).await {
+                    break;
+                }
+            }
+            scheduler.jobs.write().remove(&id);
+        });
+
+        JobHandle {
+            id,
+            scheduler: self.clone(),
+        }
+    }
+
+    async fn record_run(&self, id: u64, next: Option<SystemTime>) -> bool {
+        let stored = {
+            let mut jobs = self.jobs.write();
+            let Some(entry) = jobs.get_mut(&id) else {
+                return false;
+            };
+            entry.info.runs += 1;
+            entry.info.next_run = next;
+            entry.stored.as_mut().map(|stored| {
+                stored.next_run = next;
+                stored.clone()
+            })
+        };
+
+        let store = self.store.read().clone();
+        if let (Some(stored), Some(store)) = (stored, store) {
+            let key = stored.key.clone();
+            persist(store.clone(), key.clone(), next.map(|_| stored)).await;
+            if next.is_some() && !self.jobs.read().contains_key(&id) {
+                persist(store, key, None).await;
+            }
+        }
+        true
+    }
+
+    fn cancel_running(&self, name: &str) -> bool {
+        let mut jobs = self.jobs.write();
+        let ids: Vec<u64> = jobs.iter()
+            .filter(|(_, e)| e.info.name.as_deref() == Some(name))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            if let Some(entry) = jobs.remove(id) {
+                entry.token.cancel();
+            }
+        }
+        !ids.is_empty()
+    }
+
+    fn cancel_id(&self, id: u64) {
+        let entry = self.jobs.write().remove(&id);
+        if let Some(entry) = entry {
+            entry.token.cancel();
+            self.forget(entry.stored.as_ref());
+        }
+    }
+
+    pub fn cancel(&self, name: &str) -> bool {
+        let found = self.cancel_running(name);
+        if let Some(store) = self.store.read().clone() {
+            if let Err(e) = store.remove(name) {
+                crate::logger::log_error(&format!("Failed to remove job {}: {}", name, e));
+            }
+        }
+        found
+    }
+
+    fn forget(&self, stored: Option<&StoredJob>) {
+        if let (Some(stored), Some(store)) = (stored, self.store.read().clone()) {
+            if let Err(e) = store.remove(&stored.key) {
+                crate::logger::log_error(&format!("Failed to remove job {}: {}", stored.key, e));
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<JobInfo> {
+        self.jobs.read().values()
+            .find(|e| e.info.name.as_deref() == Some(name))
+            .map(|e| e.info.clone())
+    }
+
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self.jobs.read().values().map(|e| e.info.clone()).collect();
+        jobs.sort_by_key(|j| j.id);
+        jobs
+    }
+
+    pub async fn shutdown(&self, grace: Duration) {
+        let token = std::mem::take(&mut *self.shutdown.write());
+        token.cancel();
+        self.tracker.close();
+        if tokio::time::timeout(grace, self.tracker.wait()).await.is_err() {
+            crate::logger::log_error("Scheduler shutdown timed out with jobs still running");
+        }
+        self.tracker.reopen();
+        self.jobs.write().retain(|_, entry| !entry.token.is_cancelled());
     }
 }
 
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("jobs", &self.jobs.read().len())
+            .field("shutdown", &self.shutdown.read().is_cancelled())
+            .finish()
+    }
+}
+
+async fn persist(store: Arc<dyn JobStore>, key: String, job: Option<StoredJob>) {
+    let result = {
+        let key = key.clone();
+        tokio::task::spawn_blocking(move || match job {
+            Some(job) => store.save(&job),
+            None => store.remove(&key),
+        }).await
+    };
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => crate::logger::log_error(&format!("Failed to persist job {}: {}", key, e)),
+        Err(e) => crate::logger::log_error(&format!("Failed to persist job {}: {}", key, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::days_from_civil;
+
+    fn utc(year: i32, month: u32, day: u32, hour: i64, minute: i64) -> SystemTime {
+        let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60;
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    }
+
+    #[test]
+    fn finds_next_step_minute() {
+        let cron = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 7)), Some(utc(2025, 1, 1, 0, 15)));
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 15)), Some(utc(2025, 1, 1, 0, 30)));
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 23, 50)), Some(utc(2025, 1, 2, 0, 0)));
+    }
+
+    #[test]
+    fn matches_named_weekdays_and_months() {
+        let cron = CronSchedule::parse("30 9 * * mon").unwrap();
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 0)), Some(utc(2025, 1, 6, 9, 30)));
+        let cron = CronSchedule::parse("0 0 1 feb *").unwrap();
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 0)), Some(utc(2025, 2, 1, 0, 0)));
+    }
+
+    #[test]
+    fn day_of_month_or_day_of_week() {
+        let cron = CronSchedule::parse("0 0 13 * 5").unwrap();
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 0)), Some(utc(2025, 1, 3, 0, 0)));
+        assert_eq!(cron.next_after(utc(2025, 1, 11, 0, 0)), Some(utc(2025, 1, 13, 0, 0)));
+    }
+
+    #[test]
+    fn applies_utc_offset_and_jalali_calendar() {
+        let cron = CronSchedule::parse("0 8 * * *").unwrap().in_tehran();
+        assert_eq!(cron.next_after(utc(2025, 1, 1, 0, 0)), Some(utc(2025, 1, 1, 4, 30)));
+        let nowruz = CronSchedule::parse("0 0 1 1 *").unwrap().jalali();
+        assert_eq!(nowruz.next_after(utc(2025, 1, 1, 0, 0)), Some(utc(2025, 3, 20, 20, 30)));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("61 * * * *").is_err());
+        assert!(CronSchedule::parse("* * * * funday").is_err());
+    }
+
+    #[test]
+    fn interval_trigger_follows_previous_run() {
+        let start = utc(2025, 1, 1, 0, 0);
+        let trigger = Trigger::Interval(Duration::from_secs(60));
+        assert_eq!(trigger.next_run(Some(start), start), Some(start + Duration::from_secs(60)));
+        assert!(Trigger::At(start).next_run(Some(start), start).is_none());
+    }
+}
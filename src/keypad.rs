@@ -1,33 +1,52 @@
-use serde_json::{json, Value};
+use crate::context::{Button, ButtonType, Keypad, KeypadRow};
+use crate::exceptions::APIRequestError;
+use crate::i18n::{translate_keypad, Translator};
 
 pub struct ChatKeypadBuilder {
-    rows: Vec<Value>,
+    rows: Vec<KeypadRow>,
+    translator: Option<Translator>,
 }
 
 impl ChatKeypadBuilder {
     pub fn new() -> Self {
-        ChatKeypadBuilder { rows: Vec::new() }
+        ChatKeypadBuilder {
+            rows: Vec::new(),
+            translator: None,
+        }
     }
 
-    pub fn row(mut self, buttons: &[Value]) -> Self {
-        self.rows.push(json!({"buttons": buttons}));
+    pub fn translate<F>(mut self, translator: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.translator = Some(Box::new(translator));
         self
     }
 
-    pub fn button(&self, id: &str, text: &str, type_: Option<&str>) -> Value {
-        json!({
-            "id": id,
-            "type": type_.unwrap_or("Simple"),
-            "button_text": text
-        })
+    pub fn row(mut self, buttons: &[Button]) -> Self {
+        self.rows.push(KeypadRow { buttons: buttons.to_vec() });
+        self
+    }
+
+    pub fn button(&self, id: &str, text: &str, type_: Option<ButtonType>) -> Button {
+        Button::new(id, type_.unwrap_or_default(), text)
     }
 
-    pub fn build(self, resize_keyboard: Option<bool>, on_time_keyboard: Option<bool>) -> Value {
-        json!({
-            "rows": self.rows,
-            "resize_keyboard": resize_keyboard.unwrap_or(true),
-            "on_time_keyboard": on_time_keyboard.unwrap_or(false)
-        })
+    pub fn build(self, resize_keyboard: Option<bool>, on_time_keyboard: Option<bool>) -> Result<Keypad, APIRequestError> {
+        let keypad = self.build_unchecked(resize_keyboard, on_time_keyboard);
+        keypad.check()?;
+        Ok(keypad)
+    }
+
+    pub fn build_unchecked(mut self, resize_keyboard: Option<bool>, on_time_keyboard: Option<bool>) -> Keypad {
+        if let Some(ref translator) = self.translator {
+            translate_keypad(&mut self.rows, translator);
+        }
+        Keypad {
+            rows: self.rows,
+            resize_keyboard: Some(resize_keyboard.unwrap_or(true)),
+            on_time_keyboard: Some(on_time_keyboard.unwrap_or(false)),
+        }
     }
 }
 
@@ -36,4 +55,3 @@ impl Default for ChatKeypadBuilder {
         Self::new()
     }
 }
-
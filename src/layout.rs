@@ -0,0 +1,414 @@
+use crate::context::{Button, Keypad, KeypadRow};
+use crate::exceptions::APIRequestError;
+use crate::validation::{KeypadIssue, KeypadProblem};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl LayoutFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(LayoutFormat::Json),
+            "toml" => Some(LayoutFormat::Toml),
+            "yaml" | "yml" => Some(LayoutFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutKind {
+    #[default]
+    Inline,
+    Chat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutButton {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_key: Option<String>,
+    #[serde(flatten)]
+    pub button: Button,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layout {
+    #[serde(default)]
+    pub kind: LayoutKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resize_keyboard: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_time_keyboard: Option<bool>,
+    pub rows: Vec<Vec<LayoutButton>>,
+}
+
+impl Layout {
+    pub fn render<F>(&self, vars: &[(&str, &str)], translate: F) -> Result<Keypad, APIRequestError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let keypad = self.render_unchecked(vars, translate)?;
+        keypad.check()?;
+        Ok(keypad)
+    }
+
+    pub fn render_unchecked<F>(&self, vars: &[(&str, &str)], translate: F) -> Result<Keypad, APIRequestError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.interpolated(vars, &translate)
+    }
+
+    fn interpolated(&self, vars: &[(&str, &str)], translate: &dyn Fn(&str) -> Option<String>) -> Result<Keypad, APIRequestError> {
+        let rows = self.rows.iter()
+            .map(|row| KeypadRow {
+                buttons: row.iter().map(|b| b.resolve(translate)).collect(),
+            })
+            .collect();
+        let (resize_keyboard, on_time_keyboard) = match self.kind {
+            LayoutKind::Inline => (self.resize_keyboard, self.on_time_keyboard),
+            LayoutKind::Chat => (
+                Some(self.resize_keyboard.unwrap_or(true)),
+                Some(self.on_time_keyboard.unwrap_or(false)),
+            ),
+        };
+        let keypad = Keypad { rows, resize_keyboard, on_time_keyboard };
+        if vars.is_empty() {
+            return Ok(keypad);
+        }
+
+        let mut value = serde_json::to_value(&keypad)?;
+        interpolate_value(&mut value, vars);
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn static_issues(&self) -> Vec<KeypadIssue> {
+        let keypad = match self.interpolated(&[], &|_| None) {
+            Ok(keypad) => keypad,
+            Err(_) => return Vec::new(),
+        };
+        match keypad.validate() {
+            Ok(()) => Vec::new(),
+            Err(issues) => issues.into_iter()
+                .filter(|issue| {
+                    let button = issue.column.and_then(|c| keypad.rows.get(issue.row)?.buttons.get(c));
+                    let Some(Ok(button)) = button.map(serde_json::to_value) else {
+                        return true;
+                    };
+                    !problem_fields(&issue.problem).iter()
+                        .any(|field| button.pointer(field).is_some_and(has_placeholder))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl LayoutButton {
+    fn resolve(&self, translate: &dyn Fn(&str) -> Option<String>) -> Button {
+        let mut button = self.button.clone();
+        let text = self.text_key.as_deref()
+            .and_then(translate)
+            .or_else(|| self.text.clone())
+            .or_else(|| button.button_text.clone())
+            .or_else(|| self.text_key.clone());
+        button.button_text = text;
+        button
+    }
+}
+
+pub fn interpolate(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match vars.iter().find(|(k, _)| *k == name) {
+                    Some((_, v)) => out.push_str(v),
+                    None => out.push_str(&rest[start..start + end + 4]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn interpolate_value(value: &mut Value, vars: &[(&str, &str)]) {
+    match value {
+        Value::String(s) => *s = interpolate(s, vars),
+        Value::Array(items) => items.iter_mut().for_each(|v| interpolate_value(v, vars)),
+        Value::Object(map) => map.values_mut().for_each(|v| interpolate_value(v, vars)),
+        _ => {}
+    }
+}
+
+fn has_placeholder(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.find("{{").is_some_and(|i| s[i..].contains("}}")),
+        Value::Array(items) => items.iter().any(has_placeholder),
+        Value::Object(map) => map.values().any(has_placeholder),
+        _ => false,
+    }
+}
+
+fn problem_fields(problem: &KeypadProblem) -> Vec<String> {
+    let fields: &[&str] = match problem {
+        KeypadProblem::MissingId | KeypadProblem::DuplicateId { .. } => &["/id"],
+        KeypadProblem::MissingText => &["/button_text"],
+        KeypadProblem::InvalidLinkUrl(_) => &["/url"],
+        KeypadProblem::InvalidNumber { field, .. } => {
+            let picker = match *field {
+                "min_year" | "max_year" => "button_calendar",
+                _ => "button_number_picker",
+            };
+            return vec![format!("/{}/{}", picker, field)];
+        }
+        KeypadProblem::NumberRange { .. } => &["/button_number_picker/min_value", "/button_number_picker/max_value"],
+        KeypadProblem::NumberDefaultOutOfRange { .. } => &[
+            "/button_number_picker/min_value",
+            "/button_number_picker/max_value",
+            "/button_number_picker/default_value",
+        ],
+        KeypadProblem::PickerDefaultNotInItems { .. } => &["/button_string_picker/items", "/button_string_picker/default_value"],
+        KeypadProblem::YearRange { .. } => &["/button_calendar/min_year", "/button_calendar/max_year"],
+        _ => &[],
+    };
+    fields.iter().map(|f| f.to_string()).collect()
+}
+
+#[derive(Debug, Default)]
+struct LayoutSet {
+    layouts: HashMap<String, Layout>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardLayouts {
+    inner: Arc<RwLock<LayoutSet>>,
+}
+
+impl KeyboardLayouts {
+    pub fn parse(content: &str, format: LayoutFormat) -> Result<Self, APIRequestError> {
+        let layouts = parse_layouts(content, format)?;
+        Ok(KeyboardLayouts {
+            inner: Arc::new(RwLock::new(LayoutSet { layouts, path: None, modified: None })),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, APIRequestError> {
+        let path = path.as_ref().to_path_buf();
+        let (layouts, modified) = read_layouts(&path)?;
+        Ok(KeyboardLayouts {
+            inner: Arc::new(RwLock::new(LayoutSet {
+                layouts,
+                path: Some(path),
+                modified,
+            })),
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.inner.read().layouts.keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Layout> {
+        self.inner.read().layouts.get(name).cloned()
+    }
+
+    pub fn render(&self, name: &str, vars: &[(&str, &str)]) -> Result<Keypad, APIRequestError> {
+        self.render_with(name, vars, |_| None)
+    }
+
+    pub fn render_with<F>(&self, name: &str, vars: &[(&str, &str)], translate: F) -> Result<Keypad, APIRequestError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let layout = self.get(name)
+            .ok_or_else(|| APIRequestError::RequestFailed(format!("Unknown keyboard layout: {}", name)))?;
+        layout.render(vars, translate)
+    }
+
+    pub fn reload(&self) -> Result<(), APIRequestError> {
+        let path = match self.inner.read().path.clone() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let (layouts, modified) = read_layouts(&path)?;
+        let mut inner = self.inner.write();
+        inner.layouts = layouts;
+        inner.modified = modified;
+        Ok(())
+    }
+
+    pub fn reload_if_changed(&self) -> Result<bool, APIRequestError> {
+        let (path, known) = {
+            let inner = self.inner.read();
+            match inner.path.clone() {
+                Some(path) => (path, inner.modified),
+                None => return Ok(false),
+            }
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == known {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    pub fn watch(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let layouts = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let current = layouts.clone();
+                let reloaded = tokio::task::spawn_blocking(move || current.reload_if_changed())
+                    .await
+                    .unwrap_or_else(|e| Err(APIRequestError::RequestFailed(e.to_string())));
+                match reloaded {
+                    Ok(true) => crate::logger::log_info("Reloaded keyboard layouts"),
+                    Ok(false) => {}
+                    Err(e) => crate::logger::log_error(&format!("Keeping previous keyboard layouts: {}", e)),
+                }
+            }
+        })
+    }
+}
+
+fn read_layouts(path: &Path) -> Result<(HashMap<String, Layout>, Option<SystemTime>), APIRequestError> {
+    let format = LayoutFormat::from_path(path).ok_or_else(|| {
+        APIRequestError::RequestFailed(format!("Unknown keyboard layout format: {}", path.display()))
+    })?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok((parse_layouts(&content, format)?, modified))
+}
+
+fn parse_layouts(content: &str, format: LayoutFormat) -> Result<HashMap<String, Layout>, APIRequestError> {
+    let layouts: HashMap<String, Layout> = match format {
+        LayoutFormat::Json => serde_json::from_str(content)?,
+        LayoutFormat::Toml => toml::from_str(content)
+            .map_err(|e| APIRequestError::RequestFailed(format!("Invalid keyboard layout: {}", e)))?,
+        LayoutFormat::Yaml => serde_yaml::from_str(content)
+            .map_err(|e| APIRequestError::RequestFailed(format!("Invalid keyboard layout: {}", e)))?,
+    };
+
+    for (name, layout) in &layouts {
+        let issues = layout.static_issues();
+        if !issues.is_empty() {
+            return Err(APIRequestError::RequestFailed(format!(
+                "Invalid keyboard layout {}: {}",
+                name,
+                crate::validation::describe(&issues)
+            )));
+        }
+    }
+    Ok(layouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn layouts(value: Value) -> Result<KeyboardLayouts, APIRequestError> {
+        KeyboardLayouts::parse(&value.to_string(), LayoutFormat::Json)
+    }
+
+    #[test]
+    fn interpolates_known_placeholders_only() {
+        assert_eq!(interpolate("Hi {{ name }}, {{missing}}!", &[("name", "Ali")]), "Hi Ali, {{missing}}!");
+        assert_eq!(interpolate("{name} and {{ open", &[("name", "Ali")]), "{name} and {{ open");
+    }
+
+    #[test]
+    fn allows_placeholders_in_fields_they_make_invalid() {
+        let layouts = layouts(json!({
+            "main": {"rows": [[
+                {"id": "site", "type": "Link", "text": "Site", "url": "{{ base }}/shop"},
+                {"id": "qty", "type": "NumberPicker", "text": "Qty",
+                 "button_number_picker": {"min_value": "1", "max_value": "{{stock}}"}}
+            ]]}
+        })).unwrap();
+
+        let keypad = layouts.render("main", &[("base", "https://example.com"), ("stock", "9")]).unwrap();
+        let buttons = &keypad.rows[0].buttons;
+        assert_eq!(buttons[0].url.as_deref(), Some("https://example.com/shop"));
+        assert_eq!(buttons[1].button_number_picker.as_ref().unwrap().max_value.as_deref(), Some("9"));
+        assert!(layouts.render("main", &[("base", "nope"), ("stock", "9")]).is_err());
+    }
+
+    #[test]
+    fn keeps_issues_on_fields_without_placeholders() {
+        let result = layouts(json!({
+            "main": {"rows": [[
+                {"id": "site:{{ id }}", "type": "Link", "text": "Open {{ name }}", "url": "not a url"}
+            ]]}
+        }));
+        assert!(matches!(result, Err(APIRequestError::RequestFailed(e)) if e.contains("invalid link url")));
+
+        let duplicate = layouts(json!({
+            "main": {"rows": [[{"id": "a", "text": "{{ x }}"}, {"id": "a", "text": "{{ y }}"}]]}
+        }));
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn resolves_text_keys_and_chat_defaults() {
+        let layouts = layouts(json!({
+            "menu": {"kind": "chat", "rows": [[{"id": "help", "text_key": "buttons.help", "text": "Help"}]]}
+        })).unwrap();
+
+        let keypad = layouts.render_with("menu", &[], |key| (key == "buttons.help").then(|| "راهنما".to_string())).unwrap();
+        assert_eq!(keypad.rows[0].buttons[0].button_text.as_deref(), Some("راهنما"));
+        assert_eq!(keypad.resize_keyboard, Some(true));
+        assert_eq!(layouts.render("menu", &[]).unwrap().rows[0].buttons[0].button_text.as_deref(), Some("Help"));
+        assert!(layouts.render("missing", &[]).is_err());
+    }
+
+    #[test]
+    fn reloads_changed_files() {
+        let path = std::env::temp_dir().join(format!("layouts-{}.yaml", std::process::id()));
+        std::fs::write(&path, "main:\n  rows:\n    - - id: a\n        text: A\n").unwrap();
+        let layouts = KeyboardLayouts::load(&path).unwrap();
+        assert!(!layouts.reload_if_changed().unwrap());
+
+        std::fs::write(&path, "main:\n  rows:\n    - - id: b\n        text: B\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert!(layouts.reload_if_changed().unwrap());
+        assert_eq!(layouts.render("main", &[]).unwrap().rows[0].buttons[0].id.as_deref(), Some("b"));
+
+        std::fs::write(&path, "main: [").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(120)).unwrap();
+        assert!(layouts.reload_if_changed().is_err());
+        assert_eq!(layouts.names(), vec!["main"]);
+        std::fs::remove_file(&path).ok();
+    }
+}
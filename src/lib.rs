@@ -2,14 +2,53 @@ pub mod api;
 pub mod context;
 pub mod button;
 pub mod keypad;
+pub mod validation;
+pub mod layout;
+pub mod menu;
+pub mod pagination;
+pub mod callback_data;
+pub mod conversation;
+pub mod form;
+pub mod rich_text;
+pub mod split;
+pub mod i18n;
+pub mod normalize;
+pub mod calendar;
+pub mod template;
 pub mod jobs;
+pub mod job_store;
 pub mod exceptions;
 pub mod logger;
+pub mod broadcast;
+pub mod send;
+pub mod media;
+pub mod transfer;
+pub mod file_cache;
+pub mod album;
 
 pub use api::Robot;
-pub use context::{Message, InlineMessage};
+pub use context::{Button, ButtonType, InlineMessage, Keypad, KeypadRow, Message};
 pub use button::InlineBuilder;
 pub use keypad::ChatKeypadBuilder;
-pub use jobs::Job;
+pub use validation::{KeypadIssue, KeypadProblem};
+pub use layout::{KeyboardLayouts, Layout, LayoutButton, LayoutFormat, LayoutKind};
+pub use menu::{Menu, MenuEntry, MenuNode};
+pub use pagination::{PageItem, PageSlice, Paginator};
+pub use callback_data::{CallbackCodec, CallbackData};
+pub use conversation::{WaitFilter, Waiters};
+pub use form::{Coordinates, FieldKind, Form, FormField};
+pub use rich_text::{escape_markdown, Caption, MessageText, MetaDataPart, MetaType, RichText};
+pub use split::{MAX_CAPTION_LENGTH, MAX_TEXT_LENGTH};
+pub use i18n::{I18n, PluralCategory, Translator};
+pub use normalize::{TextNormalizer, ZwnjMode};
+pub use calendar::{CalendarDate, CalendarType, TEHRAN_UTC_OFFSET};
+pub use template::{format_date, format_number, localize_digits, Rendered, Template, Templates};
+pub use jobs::{CronSchedule, JobHandle, JobInfo, Scheduler, Trigger};
+pub use job_store::{JobStore, JsonFileJobStore, MisfirePolicy, StoredJob};
 pub use exceptions::APIRequestError;
-
+pub use send::SendMessage;
+pub use media::{InputFile, MediaKind};
+pub use album::{Album, AlbumEntry, AlbumItem, AlbumResult};
+pub use transfer::{RetryPolicy, Transfer, TransferProgress};
+pub use file_cache::{CacheKeyMode, FileIdCache, FileIdStore, JsonFileIdStore, MemoryFileIdStore};
+pub use broadcast::{Broadcast, BroadcastContent, BroadcastControl, BroadcastProgress, BroadcastReport, ChatResult, DeliveryStatus};
@@ -0,0 +1,375 @@
+use crate::exceptions::APIRequestError;
+use crate::transfer::Transfer;
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use reqwest::multipart::Part;
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_util::io::ReaderStream;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type ByteStream = std::pin::Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MediaKind {
+    File,
+    Image,
+    Voice,
+    Music,
+    Gif,
+    Video,
+}
+
+impl MediaKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaKind::File => "File",
+            MediaKind::Image => "Image",
+            MediaKind::Voice => "Voice",
+            MediaKind::Music => "Music",
+            MediaKind::Gif => "Gif",
+            MediaKind::Video => "Video",
+        }
+    }
+
+    pub fn default_file_name(&self) -> &'static str {
+        match self {
+            MediaKind::File => "file",
+            MediaKind::Image => "image.jpg",
+            MediaKind::Voice => "voice.ogg",
+            MediaKind::Music => "music.mp3",
+            MediaKind::Gif => "animation.gif",
+            MediaKind::Video => "video.mp4",
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Self {
+        match mime {
+            "image/gif" => MediaKind::Gif,
+            "audio/ogg" | "audio/opus" => MediaKind::Voice,
+            m if m.starts_with("image/") => MediaKind::Image,
+            m if m.starts_with("audio/") => MediaKind::Music,
+            m if m.starts_with("video/") => MediaKind::Video,
+            _ => MediaKind::File,
+        }
+    }
+}
+
+impl std::fmt::Display for MediaKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MediaKind {
+    type Err = APIRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "File" => Ok(MediaKind::File),
+            "Image" => Ok(MediaKind::Image),
+            "Voice" => Ok(MediaKind::Voice),
+            "Music" => Ok(MediaKind::Music),
+            "Gif" => Ok(MediaKind::Gif),
+            "Video" => Ok(MediaKind::Video),
+            other => Err(APIRequestError::RequestFailed(format!("Invalid media type: {}", other))),
+        }
+    }
+}
+
+pub fn mime_from_extension(name: &str) -> Option<&'static str> {
+    let ext = Path::new(name).extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "txt" => "text/plain",
+        "json" => "application/json",
+        "apk" => "application/vnd.android.package-archive",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+pub fn mime_from_magic(head: &[u8]) -> Option<&'static str> {
+    let mime = if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        "image/webp"
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WAVE" {
+        "audio/wav"
+    } else if head.starts_with(b"OggS") {
+        "audio/ogg"
+    } else if head.starts_with(b"ID3") || head.starts_with(&[0xFF, 0xFB]) || head.starts_with(&[0xFF, 0xF3]) {
+        "audio/mpeg"
+    } else if head.starts_with(b"fLaC") {
+        "audio/flac"
+    } else if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        if &head[8..11] == b"M4A" { "audio/mp4" } else { "video/mp4" }
+    } else if head.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        "video/webm"
+    } else if head.starts_with(b"%PDF") {
+        "application/pdf"
+    } else if head.starts_with(b"PK\x03\x04") {
+        "application/zip"
+    } else {
+        return None;
+    };
+    Some(mime)
+}
+
+pub enum InputFile {
+    Path(PathBuf),
+    Bytes {
+        name: String,
+        data: Bytes,
+    },
+    Reader {
+        name: String,
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+        length: Option<u64>,
+    },
+    Url(String),
+    FileId(String),
+}
+
+impl InputFile {
+    pub fn path(path: impl AsRef<Path>) -> Self {
+        InputFile::Path(path.as_ref().to_path_buf())
+    }
+
+    pub fn bytes(name: &str, data: impl Into<Bytes>) -> Self {
+        InputFile::Bytes {
+            name: name.to_string(),
+            data: data.into(),
+        }
+    }
+
+    pub fn reader<R>(name: &str, reader: R, length: Option<u64>) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        InputFile::Reader {
+            name: name.to_string(),
+            reader: Box::new(reader),
+            length,
+        }
+    }
+
+    pub fn url(url: &str) -> Self {
+        InputFile::Url(url.to_string())
+    }
+
+    pub fn file_id(file_id: &str) -> Self {
+        InputFile::FileId(file_id.to_string())
+    }
+
+    pub fn file_name(&self) -> Option<String> {
+        match self {
+            InputFile::Path(path) => path.file_name().and_then(|n| n.to_str()).map(|n| n.to_string()),
+            InputFile::Bytes { name, .. } | InputFile::Reader { name, .. } => Some(name.clone()),
+            InputFile::Url(url) => url.split(['?', '#']).next()
+                .and_then(|u| u.rsplit('/').next())
+                .filter(|n| !n.is_empty())
+                .map(|n| n.to_string()),
+            InputFile::FileId(_) => None,
+        }
+    }
+
+    pub fn try_clone(&self) -> Option<InputFile> {
+        match self {
+            InputFile::Path(path) => Some(InputFile::Path(path.clone())),
+            InputFile::Bytes { name, data } => Some(InputFile::Bytes {
+                name: name.clone(),
+                data: data.clone(),
+            }),
+            InputFile::Url(url) => Some(InputFile::Url(url.clone())),
+            InputFile::FileId(id) => Some(InputFile::FileId(id.clone())),
+            InputFile::Reader { .. } => None,
+        }
+    }
+
+    async fn head(&self) -> Option<Vec<u8>> {
+        match self {
+            InputFile::Path(path) => {
+                let mut file = tokio::fs::File::open(path).await.ok()?;
+                let mut head = vec![0u8; 16];
+                let read = file.read(&mut head).await.ok()?;
+                head.truncate(read);
+                Some(head)
+            }
+            InputFile::Bytes { data, .. } => Some(data[..data.len().min(16)].to_vec()),
+            _ => None,
+        }
+    }
+
+    pub async fn mime_type(&self, name: Option<&str>) -> &'static str {
+        if let Some(mime) = self.head().await.as_deref().and_then(mime_from_magic) {
+            return mime;
+        }
+        name.map(|n| n.to_string())
+            .or_else(|| self.file_name())
+            .as_deref()
+            .and_then(mime_from_extension)
+            .unwrap_or("application/octet-stream")
+    }
+
+    pub async fn media_kind(&self) -> MediaKind {
+        MediaKind::from_mime(self.mime_type(None).await)
+    }
+
+    pub(crate) async fn into_part(self, client: &reqwest::Client, transfer: &Transfer) -> Result<Part, APIRequestError> {
+        let (stream, length): (ByteStream, Option<u64>) = match self {
+            InputFile::Path(path) => {
+                let file = tokio::fs::File::open(&path).await
+                    .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read file: {}", e)))?;
+                let length = file.metadata().await
+                    .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read file: {}", e)))?
+                    .len();
+                (Box::pin(ReaderStream::new(file)), Some(length))
+            }
+            InputFile::Bytes { data, .. } => {
+                let length = data.len() as u64;
+                let chunks: Vec<std::io::Result<Bytes>> = (0..data.len())
+                    .step_by(CHUNK_SIZE)
+                    .map(|start| Ok(data.slice(start..(start + CHUNK_SIZE).min(data.len()))))
+                    .collect();
+                (Box::pin(futures_util::stream::iter(chunks)), Some(length))
+            }
+            InputFile::Reader { reader, length, .. } => (Box::pin(ReaderStream::new(reader)), length),
+            InputFile::Url(url) => {
+                let response = client.get(&url).send().await?;
+                if !response.status().is_success() {
+                    return Err(APIRequestError::RequestFailed(
+                        format!("Failed to fetch {}: {}", url, response.status())
+                    ));
+                }
+                let length = response.content_length();
+                (Box::pin(response.bytes_stream().map_err(std::io::Error::other)), length)
+            }
+            InputFile::FileId(_) => {
+                return Err(APIRequestError::RequestFailed(
+                    "A file_id cannot be uploaded again".to_string()
+                ));
+            }
+        };
+
+        let body = Body::wrap_stream(transfer.track(stream, length));
+        Ok(match length {
+            Some(len) => Part::stream_with_length(body, len),
+            None => Part::stream(body),
+        })
+    }
+}
+
+impl std::fmt::Debug for InputFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputFile::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            InputFile::Bytes { name, data } => f.debug_struct("Bytes")
+                .field("name", name)
+                .field("len", &data.len())
+                .finish(),
+            InputFile::Reader { name, length, .. } => f.debug_struct("Reader")
+                .field("name", name)
+                .field("length", length)
+                .finish(),
+            InputFile::Url(url) => f.debug_tuple("Url").field(url).finish(),
+            InputFile::FileId(id) => f.debug_tuple("FileId").field(id).finish(),
+        }
+    }
+}
+
+impl From<&str> for InputFile {
+    fn from(value: &str) -> Self {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            InputFile::url(value)
+        } else {
+            InputFile::path(value)
+        }
+    }
+}
+
+impl From<String> for InputFile {
+    fn from(value: String) -> Self {
+        InputFile::from(value.as_str())
+    }
+}
+
+impl From<PathBuf> for InputFile {
+    fn from(value: PathBuf) -> Self {
+        InputFile::Path(value)
+    }
+}
+
+impl From<&Path> for InputFile {
+    fn from(value: &Path) -> Self {
+        InputFile::path(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_mime_from_magic_bytes() {
+        assert_eq!(mime_from_magic(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(mime_from_magic(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(mime_from_magic(b"GIF89a"), Some("image/gif"));
+        assert_eq!(mime_from_magic(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(mime_from_magic(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(mime_from_magic(b"OggS\0\x02"), Some("audio/ogg"));
+        assert_eq!(mime_from_magic(b"ID3\x04"), Some("audio/mpeg"));
+        assert_eq!(mime_from_magic(b"\0\0\0\x20ftypM4A "), Some("audio/mp4"));
+        assert_eq!(mime_from_magic(b"\0\0\0\x18ftypmp42"), Some("video/mp4"));
+        assert_eq!(mime_from_magic(&[0x1A, 0x45, 0xDF, 0xA3]), Some("video/webm"));
+        assert_eq!(mime_from_magic(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(mime_from_magic(b"RIFF\0\0"), None);
+        assert_eq!(mime_from_magic(b"hello"), None);
+        assert_eq!(mime_from_magic(&[]), None);
+    }
+
+    #[test]
+    fn detects_mime_from_extension() {
+        assert_eq!(mime_from_extension("photo.JPG"), Some("image/jpeg"));
+        assert_eq!(mime_from_extension("/tmp/song.mp3"), Some("audio/mpeg"));
+        assert_eq!(mime_from_extension("voice.opus"), Some("audio/opus"));
+        assert_eq!(mime_from_extension("clip.tar.webm"), Some("video/webm"));
+        assert_eq!(mime_from_extension("README"), None);
+        assert_eq!(mime_from_extension("archive.rar"), None);
+    }
+
+    #[tokio::test]
+    async fn prefers_content_over_file_name() {
+        let png = InputFile::bytes("photo.txt", &b"\x89PNG\r\n\x1a\n\0\0"[..]);
+        assert_eq!(png.mime_type(None).await, "image/png");
+        assert_eq!(png.media_kind().await, MediaKind::Image);
+
+        let unknown = InputFile::bytes("song.ogg", &b"not magic"[..]);
+        assert_eq!(unknown.media_kind().await, MediaKind::Voice);
+        assert_eq!(InputFile::url("https://x.io/a/clip.mp4?sig=1").media_kind().await, MediaKind::Video);
+        assert_eq!(InputFile::file_id("abc").mime_type(None).await, "application/octet-stream");
+    }
+}
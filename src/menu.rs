@@ -0,0 +1,379 @@
+use crate::api::Robot;
+use crate::context::{Button, Keypad, KeypadRow, Message};
+use crate::exceptions::APIRequestError;
+use crate::send::message_id_of;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type MenuActionFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub type MenuAction = Arc<dyn Fn(Arc<Robot>, Message, String) -> MenuActionFuture + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub enum MenuEntry {
+    Submenu { node: String, label: String },
+    Action { id: String, label: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct MenuNode {
+    pub id: String,
+    pub text: String,
+    pub entries: Vec<MenuEntry>,
+    pub columns: usize,
+}
+
+impl MenuNode {
+    pub fn new(id: &str, text: &str) -> Self {
+        MenuNode {
+            id: id.to_string(),
+            text: text.to_string(),
+            entries: Vec::new(),
+            columns: 1,
+        }
+    }
+
+    pub fn submenu(mut self, node: &str, label: &str) -> Self {
+        self.entries.push(MenuEntry::Submenu {
+            node: node.to_string(),
+            label: label.to_string(),
+        });
+        self
+    }
+
+    pub fn action(mut self, id: &str, label: &str) -> Self {
+        self.entries.push(MenuEntry::Action {
+            id: id.to_string(),
+            label: label.to_string(),
+        });
+        self
+    }
+
+    pub fn columns(mut self, columns: usize) -> Self {
+        self.columns = columns.max(1);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct MenuState {
+    stack: Vec<String>,
+    current: String,
+    message_id: Option<String>,
+}
+
+impl MenuState {
+    fn enter(&mut self, node: &str) {
+        if self.current != node {
+            if !self.current.is_empty() {
+                self.stack.push(std::mem::take(&mut self.current));
+            }
+            self.current = node.to_string();
+        }
+    }
+
+    fn back(&mut self, root: &str) {
+        self.current = self.stack.pop().unwrap_or_else(|| root.to_string());
+    }
+
+    fn home(&mut self, root: &str) {
+        self.stack.clear();
+        self.current = root.to_string();
+    }
+}
+
+pub struct Menu {
+    name: String,
+    root: String,
+    nodes: HashMap<String, MenuNode>,
+    back_label: String,
+    home_label: String,
+    on_action: Option<MenuAction>,
+}
+
+impl Menu {
+    pub fn new(name: &str, root: MenuNode) -> Self {
+        let root_id = root.id.clone();
+        Menu {
+            name: name.to_string(),
+            root: root_id.clone(),
+            nodes: HashMap::from([(root_id, root)]),
+            back_label: "« Back".to_string(),
+            home_label: "Home".to_string(),
+            on_action: None,
+        }
+    }
+
+    pub fn node(mut self, node: MenuNode) -> Self {
+        self.nodes.insert(node.id.clone(), node);
+        self
+    }
+
+    pub fn back_label(mut self, label: &str) -> Self {
+        self.back_label = label.to_string();
+        self
+    }
+
+    pub fn home_label(mut self, label: &str) -> Self {
+        self.home_label = label.to_string();
+        self
+    }
+
+    pub fn on_action<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Arc<Robot>, Message, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_action = Some(Arc::new(move |bot, msg, id| Box::pin(handler(bot, msg, id))));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn open(&self, msg: &Message) -> Result<Value, APIRequestError> {
+        let mut state = MenuState {
+            current: self.root.clone(),
+            ..Default::default()
+        };
+        let response = self.send_node(&msg.bot, &msg.chat_id, &mut state).await?;
+        self.save_state(&msg.bot, &msg.chat_id, &msg.sender_id, &state);
+        Ok(response)
+    }
+
+    pub async fn navigate(&self, msg: &Message, node: &str) -> Result<Value, APIRequestError> {
+        let mut state = self.load_state(&msg.bot, &msg.chat_id, &msg.sender_id);
+        state.enter(node);
+        self.show(msg, state).await
+    }
+
+    pub async fn back(&self, msg: &Message) -> Result<Value, APIRequestError> {
+        let mut state = self.load_state(&msg.bot, &msg.chat_id, &msg.sender_id);
+        state.back(&self.root);
+        self.show(msg, state).await
+    }
+
+    pub async fn home(&self, msg: &Message) -> Result<Value, APIRequestError> {
+        let mut state = self.load_state(&msg.bot, &msg.chat_id, &msg.sender_id);
+        state.home(&self.root);
+        self.show(msg, state).await
+    }
+
+    pub fn current(&self, msg: &Message) -> Option<String> {
+        let state = self.load_state(&msg.bot, &msg.chat_id, &msg.sender_id);
+        (!state.current.is_empty()).then_some(state.current)
+    }
+
+    pub(crate) async fn handle_callback(&self, bot: Arc<Robot>, msg: Message) {
+        let Some(button_id) = msg.aux_data.as_ref().and_then(|a| a.button_id.clone()) else {
+            return;
+        };
+        let Some(command) = button_id.strip_prefix(&format!("{}:", self.name)) else {
+            return;
+        };
+
+        let result = if let Some(node) = command.strip_prefix("go:") {
+            self.navigate(&msg, node).await
+        } else if command == "back" {
+            self.back(&msg).await
+        } else if command == "home" {
+            self.home(&msg).await
+        } else if let Some(action) = command.strip_prefix("act:") {
+            if let Some(ref handler) = self.on_action {
+                handler(bot, msg, action.to_string()).await;
+            }
+            return;
+        } else {
+            return;
+        };
+
+        if let Err(e) = result {
+            crate::logger::log_error(&format!("Menu {} navigation failed: {}", self.name, e));
+        }
+    }
+
+    pub fn keypad(&self, node_id: &str, depth: usize) -> Result<Keypad, APIRequestError> {
+        let node = self.node_by_id(node_id)?;
+        let buttons: Vec<Button> = node.entries.iter()
+            .map(|entry| match entry {
+                MenuEntry::Submenu { node, label } => Button::simple(&format!("{}:go:{}", self.name, node), label),
+                MenuEntry::Action { id, label } => Button::simple(&format!("{}:act:{}", self.name, id), label),
+            })
+            .collect();
+        let mut rows: Vec<KeypadRow> = buttons.chunks(node.columns)
+            .map(|chunk| KeypadRow { buttons: chunk.to_vec() })
+            .collect();
+
+        let mut nav = Vec::new();
+        if depth > 0 {
+            nav.push(Button::simple(&format!("{}:back", self.name), &self.back_label));
+        }
+        if depth > 1 {
+            nav.push(Button::simple(&format!("{}:home", self.name), &self.home_label));
+        }
+        if !nav.is_empty() {
+            rows.push(KeypadRow { buttons: nav });
+        }
+
+        Ok(Keypad {
+            rows,
+            resize_keyboard: None,
+            on_time_keyboard: None,
+        })
+    }
+
+    fn node_by_id(&self, node_id: &str) -> Result<&MenuNode, APIRequestError> {
+        self.nodes.get(node_id)
+            .ok_or_else(|| APIRequestError::RequestFailed(format!("Unknown menu node: {}", node_id)))
+    }
+
+    async fn show(&self, msg: &Message, mut state: MenuState) -> Result<Value, APIRequestError> {
+        let node = self.node_by_id(&state.current)?;
+        let response = match edit_target(msg, &state) {
+            Some(message_id) => {
+                let keypad = self.keypad(&node.id, state.stack.len())?;
+                msg.bot.edit_message_text(&msg.chat_id, &message_id, &node.text).await?;
+                let response = msg.bot.edit_inline_keypad(&msg.chat_id, &message_id, &keypad).await?;
+                state.message_id = Some(message_id);
+                response
+            }
+            None => self.send_node(&msg.bot, &msg.chat_id, &mut state).await?,
+        };
+        self.save_state(&msg.bot, &msg.chat_id, &msg.sender_id, &state);
+        Ok(response)
+    }
+
+    async fn send_node(&self, bot: &Robot, chat_id: &str, state: &mut MenuState) -> Result<Value, APIRequestError> {
+        let node = self.node_by_id(&state.current)?;
+        let keypad = self.keypad(&node.id, state.stack.len())?;
+        let response = bot.send_message(chat_id, &node.text, None, Some(&keypad), false, None, None).await?;
+        state.message_id = message_id_of(&response).map(|id| id.to_string());
+        Ok(response)
+    }
+
+    fn session_key(&self, sender_id: &str) -> String {
+        format!("menu:{}:{}", self.name, sender_id)
+    }
+
+    fn load_state(&self, bot: &Robot, chat_id: &str, sender_id: &str) -> MenuState {
+        bot.sessions.read()
+            .get(chat_id)
+            .and_then(|s| s.get(&self.session_key(sender_id)))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_state(&self, bot: &Robot, chat_id: &str, sender_id: &str, state: &MenuState) {
+        if let Ok(value) = serde_json::to_value(state) {
+            bot.sessions.write()
+                .entry(chat_id.to_string())
+                .or_default()
+                .insert(self.session_key(sender_id), value);
+        }
+    }
+}
+
+fn edit_target(msg: &Message, state: &MenuState) -> Option<String> {
+    match msg.aux_data.is_some() && !msg.message_id.is_empty() {
+        true => Some(msg.message_id.clone()),
+        false => state.message_id.clone(),
+    }
+}
+
+impl std::fmt::Debug for Menu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Menu")
+            .field("name", &self.name)
+            .field("root", &self.root)
+            .field("nodes", &self.nodes.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn robot() -> Arc<Robot> {
+        Arc::new(Robot::new("test-token".to_string(), None, None, None, None, None))
+    }
+
+    fn menu() -> Menu {
+        Menu::new("shop", MenuNode::new("root", "Shop").submenu("fruits", "Fruits"))
+            .node(MenuNode::new("fruits", "Fruits").submenu("apples", "Apples").action("all", "All"))
+            .node(MenuNode::new("apples", "Apples").action("red", "Red"))
+    }
+
+    fn button_ids(keypad: &Keypad) -> Vec<Vec<String>> {
+        keypad.rows.iter()
+            .map(|row| row.buttons.iter().map(|b| b.id.clone().unwrap_or_default()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn back_and_home_follow_the_stack() {
+        let mut state = MenuState { current: "root".to_string(), ..Default::default() };
+        state.enter("fruits");
+        state.enter("fruits");
+        state.enter("apples");
+        assert_eq!(state.stack, vec!["root", "fruits"]);
+
+        state.back("root");
+        assert_eq!(state.current, "fruits");
+        assert_eq!(state.stack, vec!["root"]);
+
+        state.enter("apples");
+        state.home("root");
+        assert_eq!(state.current, "root");
+        assert!(state.stack.is_empty());
+
+        state.back("root");
+        assert_eq!(state.current, "root");
+    }
+
+    #[test]
+    fn keypad_adds_back_and_home_by_depth() {
+        let menu = menu();
+        assert_eq!(button_ids(&menu.keypad("root", 0).unwrap()), vec![vec!["shop:go:fruits"]]);
+        assert_eq!(
+            button_ids(&menu.keypad("fruits", 1).unwrap()),
+            vec![vec!["shop:go:apples"], vec!["shop:act:all"], vec!["shop:back"]]
+        );
+        assert_eq!(
+            button_ids(&menu.keypad("apples", 2).unwrap()),
+            vec![vec!["shop:act:red"], vec!["shop:back", "shop:home"]]
+        );
+        assert!(menu.keypad("missing", 0).is_err());
+    }
+
+    #[test]
+    fn callbacks_edit_the_clicked_message() {
+        let bot = robot();
+        let state = MenuState { message_id: Some("10".to_string()), ..Default::default() };
+        let click = Message::new(bot.clone(), "c".into(), "".into(), "u".into(), None, Some(json!({
+            "message_id": "42",
+            "aux_data": {"button_id": "shop:back"}
+        })));
+        assert_eq!(edit_target(&click, &state).as_deref(), Some("42"));
+
+        let text = Message::new(bot, "c".into(), "7".into(), "u".into(), Some("menu".into()), None);
+        assert_eq!(edit_target(&text, &state).as_deref(), Some("10"));
+    }
+
+    #[test]
+    fn state_is_kept_per_user() {
+        let bot = robot();
+        let menu = menu();
+        let mut state = MenuState { current: "root".to_string(), ..Default::default() };
+        state.enter("fruits");
+        menu.save_state(&bot, "c", "alice", &state);
+
+        assert_eq!(menu.load_state(&bot, "c", "alice").stack, vec!["root"]);
+        assert!(menu.load_state(&bot, "c", "bob").current.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::api::Robot;
+use crate::context::Message;
+use std::borrow::Cow;
+
+const ZWNJ: char = '\u{200c}';
+const TATWEEL: char = '\u{0640}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZwnjMode {
+    Keep,
+    #[default]
+    Trim,
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextNormalizer {
+    unify_letters: bool,
+    convert_digits: bool,
+    strip_bidi: bool,
+    zwnj: ZwnjMode,
+    trim: bool,
+}
+
+impl TextNormalizer {
+    pub fn new() -> Self {
+        TextNormalizer {
+            unify_letters: true,
+            convert_digits: true,
+            strip_bidi: true,
+            zwnj: ZwnjMode::Trim,
+            trim: true,
+        }
+    }
+
+    pub fn unify_letters(mut self, enabled: bool) -> Self {
+        self.unify_letters = enabled;
+        self
+    }
+
+    pub fn convert_digits(mut self, enabled: bool) -> Self {
+        self.convert_digits = enabled;
+        self
+    }
+
+    pub fn strip_bidi(mut self, enabled: bool) -> Self {
+        self.strip_bidi = enabled;
+        self
+    }
+
+    pub fn zwnj(mut self, mode: ZwnjMode) -> Self {
+        self.zwnj = mode;
+        self
+    }
+
+    pub fn trim(mut self, enabled: bool) -> Self {
+        self.trim = enabled;
+        self
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for c in text.chars() {
+            if self.strip_bidi && is_bidi_control(c) {
+                continue;
+            }
+            if self.unify_letters && c == TATWEEL {
+                continue;
+            }
+            if self.zwnj == ZwnjMode::Remove && c == ZWNJ {
+                continue;
+            }
+            out.push(match c {
+                'ي' | 'ى' if self.unify_letters => 'ی',
+                'ك' if self.unify_letters => 'ک',
+                '٠'..='٩' if self.convert_digits => shift_digit(c, '٠'),
+                '۰'..='۹' if self.convert_digits => shift_digit(c, '۰'),
+                _ => c,
+            });
+        }
+
+        if self.zwnj == ZwnjMode::Trim {
+            out = trim_zwnj(&out);
+        }
+        if self.trim {
+            let trimmed = out.trim();
+            if trimmed.len() != out.len() {
+                out = trimmed.to_string();
+            }
+        }
+        out
+    }
+}
+
+impl Default for TextNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Robot {
+    pub fn set_text_normalizer(&self, normalizer: Option<TextNormalizer>) {
+        *self.text_normalizer.write() = normalizer;
+    }
+
+    pub fn normalize_text(&self, text: &str) -> String {
+        match self.text_normalizer.read().as_ref() {
+            Some(normalizer) => normalizer.normalize(text),
+            None => text.to_string(),
+        }
+    }
+}
+
+impl Message {
+    pub(crate) fn for_filters(&self) -> Cow<'_, Message> {
+        match self.bot.text_normalizer.read().is_some() && self.normalized_text != self.text {
+            true => Cow::Owned(Message {
+                text: self.normalized_text.clone(),
+                ..self.clone()
+            }),
+            false => Cow::Borrowed(self),
+        }
+    }
+}
+
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(c,
+        '\u{061c}'
+        | '\u{200e}'
+        | '\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{feff}')
+}
+
+fn shift_digit(c: char, zero: char) -> char {
+    char::from_digit(c as u32 - zero as u32, 10).unwrap_or(c)
+}
+
+fn trim_zwnj(text: &str) -> String {
+    let joins = |c: Option<char>| c.is_some_and(|c| !c.is_whitespace() && c != ZWNJ);
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ZWNJ && !(joins(out.chars().next_back()) && joins(chars.peek().copied())) {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn robot() -> Arc<Robot> {
+        Arc::new(Robot::new("test-token".to_string(), None, None, None, None, None))
+    }
+
+    fn message(bot: &Arc<Robot>, text: &str) -> Message {
+        Message::new(bot.clone(), "c".into(), "1".into(), "u".into(), Some(text.to_string()), None)
+    }
+
+    #[test]
+    fn unifies_letters_and_digits() {
+        let normalizer = TextNormalizer::new();
+        assert_eq!(normalizer.normalize("علي كتاب"), "علی کتاب");
+        assert_eq!(normalizer.normalize("۱۲۳ و ٤٥٦"), "123 و 456");
+        assert_eq!(normalizer.normalize("سـلام"), "سلام");
+        assert_eq!(normalizer.normalize("\u{200f}  سلام\u{202b} "), "سلام");
+    }
+
+    #[test]
+    fn handles_zwnj_modes() {
+        let text = "\u{200c}می\u{200c}\u{200c}روم \u{200c}خانه\u{200c}";
+        assert_eq!(TextNormalizer::new().normalize(text), "می\u{200c}روم خانه");
+        assert_eq!(TextNormalizer::new().zwnj(ZwnjMode::Remove).normalize(text), "میروم خانه");
+        assert_eq!(TextNormalizer::new().zwnj(ZwnjMode::Keep).trim(false).normalize(text), text);
+    }
+
+    #[test]
+    fn steps_can_be_disabled() {
+        let normalizer = TextNormalizer::new()
+            .unify_letters(false)
+            .convert_digits(false)
+            .strip_bidi(false)
+            .trim(false);
+        assert_eq!(normalizer.normalize(" ي ۱\u{200e}"), " ي ۱\u{200e}");
+    }
+
+    #[test]
+    fn filters_see_normalized_text_only_with_a_normalizer() {
+        let bot = robot();
+        let matched = Arc::new(AtomicUsize::new(0));
+        let counter = matched.clone();
+        bot.on_message(Some(Box::new(|msg: &Message| msg.text.as_deref() == Some("کد 12"))), None, move |_, msg| {
+            assert_eq!(msg.text.as_deref(), Some("كد ۱۲"));
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let handler = |bot: &Arc<Robot>| bot.message_handlers.read()[0](bot.clone(), message(bot, "كد ۱۲"));
+
+        handler(&bot);
+        assert_eq!(matched.load(Ordering::SeqCst), 0);
+
+        bot.set_text_normalizer(Some(TextNormalizer::new()));
+        handler(&bot);
+        assert_eq!(matched.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::api::Robot;
+use crate::context::{Button, Keypad, KeypadRow, Message};
+use crate::exceptions::APIRequestError;
+use crate::send::message_id_of;
+use crate::validation::MAX_BUTTONS_PER_ROW;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type PageSource<T> = Arc<dyn Fn(usize, usize) -> BoxFuture<Result<PageSlice<T>, APIRequestError>> + Send + Sync>;
+type PageRenderer<T> = Arc<dyn Fn(&T) -> PageItem + Send + Sync>;
+type PageTitle = Arc<dyn Fn(usize, usize, usize) -> String + Send + Sync>;
+type SelectHandler = Arc<dyn Fn(Arc<Robot>, Message, String) -> BoxFuture<()> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct PageSlice<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum PageItem {
+    Button { id: String, label: String },
+    Line(String),
+}
+
+impl PageItem {
+    pub fn button(id: &str, label: &str) -> Self {
+        PageItem::Button {
+            id: id.to_string(),
+            label: label.to_string(),
+        }
+    }
+
+    pub fn line(text: &str) -> Self {
+        PageItem::Line(text.to_string())
+    }
+}
+
+pub struct Paginator<T> {
+    name: String,
+    source: PageSource<T>,
+    renderer: PageRenderer<T>,
+    title: PageTitle,
+    on_select: Option<SelectHandler>,
+    page_size: usize,
+    numbered_buttons: usize,
+}
+
+impl<T: Send + Sync + 'static> Paginator<T> {
+    pub fn new<F, Fut, R>(name: &str, source: F, renderer: R) -> Self
+    where
+        F: Fn(usize, usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<PageSlice<T>, APIRequestError>> + Send + 'static,
+        R: Fn(&T) -> PageItem + Send + Sync + 'static,
+    {
+        Paginator {
+            name: name.to_string(),
+            source: Arc::new(move |offset, limit| Box::pin(source(offset, limit))),
+            renderer: Arc::new(renderer),
+            title: Arc::new(|page, pages, _| format!("Page {}/{}", page, pages)),
+            on_select: None,
+            page_size: 10,
+            numbered_buttons: 5,
+        }
+    }
+
+    pub fn from_items<R>(name: &str, items: Vec<T>, renderer: R) -> Self
+    where
+        T: Clone,
+        R: Fn(&T) -> PageItem + Send + Sync + 'static,
+    {
+        let items = Arc::new(items);
+        Self::new(
+            name,
+            move |offset, limit| {
+                let items = Arc::clone(&items);
+                async move {
+                    Ok(PageSlice {
+                        items: items.iter().skip(offset).take(limit).cloned().collect(),
+                        total: items.len(),
+                    })
+                }
+            },
+            renderer,
+        )
+    }
+
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn numbered_buttons(mut self, count: usize) -> Self {
+        self.numbered_buttons = count;
+        self
+    }
+
+    pub fn title<F>(mut self, title: F) -> Self
+    where
+        F: Fn(usize, usize, usize) -> String + Send + Sync + 'static,
+    {
+        self.title = Arc::new(title);
+        self
+    }
+
+    pub fn on_select<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Arc<Robot>, Message, String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_select = Some(Arc::new(move |bot, msg, id| Box::pin(handler(bot, msg, id))));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn open(&self, msg: &Message) -> Result<Value, APIRequestError> {
+        self.open_at(msg, 1).await
+    }
+
+    pub async fn open_at(&self, msg: &Message, page: usize) -> Result<Value, APIRequestError> {
+        let (text, keypad, page) = self.render_page(page).await?;
+        let keypad = (!keypad.rows.is_empty()).then_some(&keypad);
+        let response = msg.bot.send_message(&msg.chat_id, &text, None, keypad, false, None, None).await?;
+        let message_id = message_id_of(&response).map(|id| id.to_string());
+        self.save_state(msg, page, message_id);
+        Ok(response)
+    }
+
+    pub async fn show_page(&self, msg: &Message, page: usize) -> Result<Value, APIRequestError> {
+        let Some(message_id) = self.message_id(msg) else {
+            return self.open_at(msg, page).await;
+        };
+        let (text, keypad, page) = self.render_page(page).await?;
+        msg.bot.edit_message_text(&msg.chat_id, &message_id, &text).await?;
+        let response = match keypad.rows.is_empty() {
+            true => msg.bot.clear_inline_keypad(&msg.chat_id, &message_id).await?,
+            false => msg.bot.edit_inline_keypad(&msg.chat_id, &message_id, &keypad).await?,
+        };
+        self.save_state(msg, page, Some(message_id));
+        Ok(response)
+    }
+
+    pub fn current_page(&self, msg: &Message) -> Option<usize> {
+        self.state(msg)
+            .and_then(|s| s.get("page").and_then(|v| v.as_u64()))
+            .map(|p| p as usize)
+    }
+
+    pub async fn render_page(&self, page: usize) -> Result<(String, Keypad, usize), APIRequestError> {
+        let page = page.max(1);
+        let mut slice = (self.source)((page - 1) * self.page_size, self.page_size).await?;
+        let pages = slice.total.div_ceil(self.page_size).max(1);
+        let page = if page > pages {
+            slice = (self.source)((pages - 1) * self.page_size, self.page_size).await?;
+            pages
+        } else {
+            page
+        };
+
+        let mut lines = vec![(self.title)(page, pages, slice.total)];
+        let mut rows = Vec::new();
+        for item in &slice.items {
+            match (self.renderer)(item) {
+                PageItem::Button { id, label } => rows.push(KeypadRow {
+                    buttons: vec![Button::simple(&format!("{}:item:{}", self.name, id), &label)],
+                }),
+                PageItem::Line(text) => lines.push(text),
+            }
+        }
+        if pages > 1 {
+            rows.push(KeypadRow { buttons: self.navigation(page, pages) });
+        }
+
+        let keypad = Keypad {
+            rows,
+            resize_keyboard: None,
+            on_time_keyboard: None,
+        };
+        Ok((lines.join("\n"), keypad, page))
+    }
+
+    fn navigation(&self, page: usize, pages: usize) -> Vec<Button> {
+        let page_button = |action: &str, target: usize, label: String| {
+            Button::simple(&format!("{}:{}:{}", self.name, action, target), &label)
+        };
+
+        let mut buttons = Vec::new();
+        if page > 1 {
+            buttons.push(page_button("prev", page - 1, "‹".to_string()));
+        }
+        if self.numbered_buttons > 0 {
+            let window = self.numbered_buttons.min(MAX_BUTTONS_PER_ROW - 2).min(pages);
+            let start = page.saturating_sub(window / 2).max(1).min(pages - window + 1);
+            for target in start..start + window {
+                if target == page {
+                    buttons.push(Button::simple(&format!("{}:current", self.name), &format!("· {} ·", target)));
+                } else {
+                    buttons.push(page_button("page", target, target.to_string()));
+                }
+            }
+        }
+        if page < pages {
+            buttons.push(page_button("next", page + 1, "›".to_string()));
+        }
+        buttons
+    }
+
+    pub(crate) async fn handle_callback(&self, bot: Arc<Robot>, msg: Message) {
+        let Some(button_id) = msg.aux_data.as_ref().and_then(|a| a.button_id.clone()) else {
+            return;
+        };
+        let Some(command) = button_id.strip_prefix(&format!("{}:", self.name)) else {
+            return;
+        };
+
+        let target = ["page:", "prev:", "next:"].iter()
+            .find_map(|action| command.strip_prefix(action))
+            .and_then(|p| p.parse::<usize>().ok());
+        if let Some(page) = target {
+            if let Err(e) = self.show_page(&msg, page).await {
+                crate::logger::log_error(&format!("Paginator {} failed to show page {}: {}", self.name, page, e));
+            }
+        } else if let Some(id) = command.strip_prefix("item:") {
+            if let Some(ref handler) = self.on_select {
+                handler(bot, msg, id.to_string()).await;
+            }
+        }
+    }
+
+    fn session_key(&self, sender_id: &str) -> String {
+        format!("pager:{}:{}", self.name, sender_id)
+    }
+
+    fn state(&self, msg: &Message) -> Option<Value> {
+        msg.bot.sessions.read()
+            .get(&msg.chat_id)
+            .and_then(|s| s.get(&self.session_key(&msg.sender_id)))
+            .cloned()
+    }
+
+    fn message_id(&self, msg: &Message) -> Option<String> {
+        if msg.aux_data.is_some() && !msg.message_id.is_empty() {
+            return Some(msg.message_id.clone());
+        }
+        self.state(msg)
+            .and_then(|s| s.get("message_id").and_then(|v| v.as_str()).map(|id| id.to_string()))
+    }
+
+    fn save_state(&self, msg: &Message, page: usize, message_id: Option<String>) {
+        msg.bot.sessions.write()
+            .entry(msg.chat_id.clone())
+            .or_default()
+            .insert(self.session_key(&msg.sender_id), json!({"page": page, "message_id": message_id}));
+    }
+}
+
+impl<T> std::fmt::Debug for Paginator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Paginator")
+            .field("name", &self.name)
+            .field("page_size", &self.page_size)
+            .field("numbered_buttons", &self.numbered_buttons)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pager(count: usize) -> Paginator<usize> {
+        Paginator::from_items("pg", (1..=count).collect(), |n| PageItem::button(&n.to_string(), &n.to_string()))
+    }
+
+    fn ids(buttons: &[Button]) -> Vec<String> {
+        buttons.iter().map(|b| b.id.clone().unwrap_or_default()).collect()
+    }
+
+    #[tokio::test]
+    async fn render_page_clamps_to_available_pages() {
+        let pager = pager(25);
+
+        let (text, keypad, page) = pager.render_page(0).await.unwrap();
+        assert_eq!(page, 1);
+        assert_eq!(text, "Page 1/3");
+        assert_eq!(keypad.rows.len(), 11);
+        assert_eq!(ids(&keypad.rows[0].buttons), vec!["pg:item:1"]);
+
+        let (text, keypad, page) = pager.render_page(99).await.unwrap();
+        assert_eq!(page, 3);
+        assert_eq!(text, "Page 3/3");
+        assert_eq!(ids(&keypad.rows[0].buttons), vec!["pg:item:21"]);
+        assert_eq!(keypad.rows.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn single_page_has_no_navigation_and_lines_go_to_text() {
+        let pager = Paginator::from_items("pg", vec!["a", "b"], |s| PageItem::line(s));
+        let (text, keypad, _) = pager.render_page(1).await.unwrap();
+        assert_eq!(text, "Page 1/1\na\nb");
+        assert!(keypad.rows.is_empty());
+    }
+
+    #[test]
+    fn navigation_window_follows_the_current_page() {
+        let pager = pager(0);
+        assert_eq!(
+            ids(&pager.navigation(10, 20)),
+            vec!["pg:prev:9", "pg:page:8", "pg:page:9", "pg:current", "pg:page:11", "pg:page:12", "pg:next:11"]
+        );
+        assert_eq!(
+            ids(&pager.navigation(1, 20)),
+            vec!["pg:current", "pg:page:2", "pg:page:3", "pg:page:4", "pg:page:5", "pg:next:2"]
+        );
+        assert_eq!(
+            ids(&pager.navigation(20, 20)),
+            vec!["pg:prev:19", "pg:page:16", "pg:page:17", "pg:page:18", "pg:page:19", "pg:current"]
+        );
+        assert_eq!(ids(&pager.navigation(2, 3)), vec!["pg:prev:1", "pg:page:1", "pg:current", "pg:page:3", "pg:next:3"]);
+    }
+
+    #[test]
+    fn navigation_fits_in_one_row() {
+        let pager = pager(0).numbered_buttons(20);
+        for page in [1, 2, 15, 29, 30] {
+            let buttons = pager.navigation(page, 30);
+            assert!(buttons.len() <= MAX_BUTTONS_PER_ROW, "page {} has {} buttons", page, buttons.len());
+        }
+        assert_eq!(ids(&pager.numbered_buttons(0).navigation(2, 3)), vec!["pg:prev:1", "pg:next:3"]);
+    }
+}
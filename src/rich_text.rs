@@ -0,0 +1,516 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const ESCAPED: &[char] = &['\\', '*', '_', '~', '-', '|', '`', '[', ']', '(', ')'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MetaType {
+    Bold,
+    Italic,
+    Mono,
+    Strike,
+    Underline,
+    Spoiler,
+    Link,
+    MentionText,
+    Pre,
+    Quote,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetaDataPart {
+    #[serde(rename = "type")]
+    pub type_: MetaType,
+    pub from_index: usize,
+    pub length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mention_text_user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl MetaDataPart {
+    pub fn new(type_: MetaType, from_index: usize, length: usize) -> Self {
+        MetaDataPart {
+            type_,
+            from_index,
+            length,
+            link_url: None,
+            mention_text_user_id: None,
+            language: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RichText {
+    text: String,
+    parts: Vec<MetaDataPart>,
+}
+
+impl RichText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn markdown(source: &str) -> Self {
+        MarkdownParser::new(source).parse()
+    }
+
+    pub(crate) fn from_parts(text: &str, parts: Vec<MetaDataPart>) -> Self {
+        RichText {
+            text: text.to_string(),
+            parts,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn parts(&self) -> &[MetaDataPart] {
+        &self.parts
+    }
+
+    pub fn utf16_len(&self) -> usize {
+        utf16_len(&self.text)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn metadata(&self) -> Option<Value> {
+        (!self.parts.is_empty()).then(|| json!({"meta_data_parts": self.parts}))
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self
+    }
+
+    pub fn styled(self, text: &str, type_: MetaType) -> Self {
+        self.push_part(text, MetaDataPart::new(type_, 0, 0))
+    }
+
+    pub fn bold(self, text: &str) -> Self {
+        self.styled(text, MetaType::Bold)
+    }
+
+    pub fn italic(self, text: &str) -> Self {
+        self.styled(text, MetaType::Italic)
+    }
+
+    pub fn mono(self, text: &str) -> Self {
+        self.styled(text, MetaType::Mono)
+    }
+
+    pub fn strike(self, text: &str) -> Self {
+        self.styled(text, MetaType::Strike)
+    }
+
+    pub fn underline(self, text: &str) -> Self {
+        self.styled(text, MetaType::Underline)
+    }
+
+    pub fn spoiler(self, text: &str) -> Self {
+        self.styled(text, MetaType::Spoiler)
+    }
+
+    pub fn quote(self, text: &str) -> Self {
+        self.styled(text, MetaType::Quote)
+    }
+
+    pub fn pre(self, code: &str, language: Option<&str>) -> Self {
+        let mut part = MetaDataPart::new(MetaType::Pre, 0, 0);
+        part.language = language.map(|l| l.to_string());
+        self.push_part(code, part)
+    }
+
+    pub fn link(self, text: &str, url: &str) -> Self {
+        let mut part = MetaDataPart::new(MetaType::Link, 0, 0);
+        part.link_url = Some(url.to_string());
+        self.push_part(text, part)
+    }
+
+    pub fn mention(self, text: &str, user_id: &str) -> Self {
+        let mut part = MetaDataPart::new(MetaType::MentionText, 0, 0);
+        part.mention_text_user_id = Some(user_id.to_string());
+        self.push_part(text, part)
+    }
+
+    pub fn append(mut self, other: RichText) -> Self {
+        let offset = self.utf16_len();
+        self.text.push_str(&other.text);
+        self.parts.extend(other.parts.into_iter().map(|mut part| {
+            part.from_index += offset;
+            part
+        }));
+        self
+    }
+
+    pub fn wrap(mut self, type_: MetaType) -> Self {
+        let length = self.utf16_len();
+        if length > 0 {
+            self.parts.push(MetaDataPart::new(type_, 0, length));
+        }
+        self
+    }
+
+    fn push_part(mut self, text: &str, mut part: MetaDataPart) -> Self {
+        part.from_index = self.utf16_len();
+        part.length = utf16_len(text);
+        self.text.push_str(text);
+        if part.length > 0 {
+            self.parts.push(part);
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for RichText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if ESCAPED.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+pub(crate) fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageText {
+    pub text: String,
+    pub metadata: Option<Value>,
+}
+
+impl MessageText {
+    pub fn plain(text: &str) -> Self {
+        MessageText {
+            text: text.to_string(),
+            metadata: None,
+        }
+    }
+
+    pub fn markdown(source: &str) -> Self {
+        RichText::markdown(source).into()
+    }
+
+    pub(crate) fn apply(&self, payload: &mut Value) {
+        payload["text"] = json!(self.text);
+        if let Some(ref metadata) = self.metadata {
+            payload["metadata"] = metadata.clone();
+        }
+    }
+}
+
+impl From<&str> for MessageText {
+    fn from(value: &str) -> Self {
+        MessageText::plain(value)
+    }
+}
+
+impl From<String> for MessageText {
+    fn from(text: String) -> Self {
+        MessageText { text, metadata: None }
+    }
+}
+
+impl From<&String> for MessageText {
+    fn from(value: &String) -> Self {
+        MessageText::plain(value)
+    }
+}
+
+impl From<RichText> for MessageText {
+    fn from(value: RichText) -> Self {
+        MessageText {
+            metadata: value.metadata(),
+            text: value.text,
+        }
+    }
+}
+
+impl From<&RichText> for MessageText {
+    fn from(value: &RichText) -> Self {
+        MessageText {
+            text: value.text.clone(),
+            metadata: value.metadata(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Caption(pub Option<MessageText>);
+
+impl Caption {
+    pub(crate) fn apply(&self, payload: &mut Value) {
+        if let Some(ref text) = self.0 {
+            text.apply(payload);
+        }
+    }
+}
+
+impl From<Option<&str>> for Caption {
+    fn from(value: Option<&str>) -> Self {
+        Caption(value.map(MessageText::plain))
+    }
+}
+
+impl From<&str> for Caption {
+    fn from(value: &str) -> Self {
+        Caption(Some(value.into()))
+    }
+}
+
+impl From<String> for Caption {
+    fn from(value: String) -> Self {
+        Caption(Some(value.into()))
+    }
+}
+
+impl From<MessageText> for Caption {
+    fn from(value: MessageText) -> Self {
+        Caption(Some(value))
+    }
+}
+
+impl From<RichText> for Caption {
+    fn from(value: RichText) -> Self {
+        Caption(Some(value.into()))
+    }
+}
+
+impl From<&RichText> for Caption {
+    fn from(value: &RichText) -> Self {
+        Caption(Some(value.into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Bold,
+    Italic,
+    Strike,
+    Underline,
+    Spoiler,
+    Link,
+}
+
+impl Marker {
+    const PAIRED: [(&'static str, Marker); 5] = [
+        ("**", Marker::Bold),
+        ("__", Marker::Italic),
+        ("~~", Marker::Strike),
+        ("--", Marker::Underline),
+        ("||", Marker::Spoiler),
+    ];
+
+    fn meta_type(self) -> MetaType {
+        match self {
+            Marker::Bold => MetaType::Bold,
+            Marker::Italic => MetaType::Italic,
+            Marker::Strike => MetaType::Strike,
+            Marker::Underline => MetaType::Underline,
+            Marker::Spoiler => MetaType::Spoiler,
+            Marker::Link => MetaType::Link,
+        }
+    }
+}
+
+struct MarkdownParser<'a> {
+    src: &'a str,
+    pos: usize,
+    out: RichText,
+    open: Vec<(Marker, usize)>,
+}
+
+impl<'a> MarkdownParser<'a> {
+    fn new(src: &'a str) -> Self {
+        MarkdownParser {
+            src,
+            pos: 0,
+            out: RichText::new(),
+            open: Vec::new(),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn emit(&mut self, text: &str) {
+        self.out.text.push_str(text);
+    }
+
+    fn close(&mut self, index: usize) -> MetaDataPart {
+        let (marker, start) = self.open.remove(index);
+        MetaDataPart::new(marker.meta_type(), start, self.out.utf16_len() - start)
+    }
+
+    fn push(&mut self, part: MetaDataPart) {
+        if part.length > 0 {
+            self.out.parts.push(part);
+        }
+    }
+
+    fn parse(mut self) -> RichText {
+        while let Some(c) = self.rest().chars().next() {
+            let rest = self.rest();
+
+            if c == '\\' {
+                let escaped = rest[1..].chars().next().filter(|n| ESCAPED.contains(n));
+                match escaped {
+                    Some(n) => {
+                        self.emit(&n.to_string());
+                        self.pos += 1 + n.len_utf8();
+                    }
+                    None => {
+                        self.emit("\\");
+                        self.pos += 1;
+                    }
+                }
+                continue;
+            }
+
+            if let Some(body) = rest.strip_prefix("```") {
+                if let Some(end) = body.find("```") {
+                    let block = &body[..end];
+                    let (language, code) = match block.split_once('\n') {
+                        Some((first, code)) if !first.trim().is_empty() && !first.contains(' ') => {
+                            (Some(first.trim()), code)
+                        }
+                        Some(("", code)) => (None, code),
+                        _ => (None, block),
+                    };
+                    let mut part = MetaDataPart::new(MetaType::Pre, self.out.utf16_len(), utf16_len(code));
+                    part.language = language.map(|l| l.to_string());
+                    self.emit(code);
+                    self.push(part);
+                    self.pos += 3 + end + 3;
+                    continue;
+                }
+            }
+
+            if let Some(body) = rest.strip_prefix('`') {
+                if let Some(end) = body.find('`') {
+                    let code = &body[..end];
+                    let part = MetaDataPart::new(MetaType::Mono, self.out.utf16_len(), utf16_len(code));
+                    self.emit(code);
+                    self.push(part);
+                    self.pos += 1 + end + 1;
+                    continue;
+                }
+            }
+
+            if let Some(&(token, marker)) = Marker::PAIRED.iter().find(|(token, _)| rest.starts_with(token)) {
+                if let Some(index) = self.open.iter().rposition(|(m, _)| *m == marker) {
+                    let part = self.close(index);
+                    self.push(part);
+                    self.pos += token.len();
+                    continue;
+                }
+                if rest[token.len()..].contains(token) {
+                    self.open.push((marker, self.out.utf16_len()));
+                    self.pos += token.len();
+                    continue;
+                }
+            }
+
+            if c == '[' && link_target(&rest[1..]).is_some() {
+                self.open.push((Marker::Link, self.out.utf16_len()));
+                self.pos += 1;
+                continue;
+            }
+
+            if let Some(tail) = rest.strip_prefix("](") {
+                if let Some(index) = self.open.iter().rposition(|(m, _)| *m == Marker::Link) {
+                    if let Some(end) = tail.find(')') {
+                        let target = tail[..end].trim();
+                        let mut part = self.close(index);
+                        if target.contains(':') {
+                            part.link_url = Some(target.to_string());
+                        } else {
+                            part.type_ = MetaType::MentionText;
+                            part.mention_text_user_id = Some(target.trim_start_matches('@').to_string());
+                        }
+                        self.push(part);
+                        self.pos += 2 + end + 1;
+                        continue;
+                    }
+                }
+            }
+
+            self.emit(&c.to_string());
+            self.pos += c.len_utf8();
+        }
+
+        self.out.parts.sort_by_key(|p| (p.from_index, std::cmp::Reverse(p.length)));
+        self.out
+    }
+}
+
+fn link_target(after_bracket: &str) -> Option<usize> {
+    let close = after_bracket.find("](")?;
+    after_bracket[close + 2..].find(')').map(|end| close + 2 + end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_markdown_into_parts() {
+        let text = RichText::markdown("**bold** and __italic__");
+        assert_eq!(text.as_str(), "bold and italic");
+        assert_eq!(text.parts(), &[
+            MetaDataPart::new(MetaType::Bold, 0, 4),
+            MetaDataPart::new(MetaType::Italic, 9, 6),
+        ]);
+    }
+
+    #[test]
+    fn counts_offsets_in_utf16_units() {
+        let text = RichText::markdown("😀 **سلام** `x`");
+        assert_eq!(text.as_str(), "😀 سلام x");
+        assert_eq!(text.utf16_len(), 9);
+        assert_eq!(text.parts(), &[
+            MetaDataPart::new(MetaType::Bold, 3, 4),
+            MetaDataPart::new(MetaType::Mono, 8, 1),
+        ]);
+    }
+
+    #[test]
+    fn handles_links_code_blocks_and_escapes() {
+        let text = RichText::markdown("[site](https://rubika.ir) \\*raw\\*");
+        assert_eq!(text.as_str(), "site *raw*");
+        assert_eq!(text.parts().len(), 1);
+        assert_eq!(text.parts()[0].type_, MetaType::Link);
+        assert_eq!(text.parts()[0].link_url.as_deref(), Some("https://rubika.ir"));
+
+        let code = RichText::markdown("```rust\nfn main() {}```");
+        assert_eq!(code.as_str(), "fn main() {}");
+        assert_eq!(code.parts()[0].language.as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn builder_matches_markdown() {
+        let built = RichText::new().bold("bold").text(" and ").italic("italic");
+        assert_eq!(built, RichText::markdown("**bold** and __italic__"));
+        assert_eq!(escape_markdown("a*b"), "a\\*b");
+        assert_eq!(RichText::markdown(&escape_markdown("**x**")).as_str(), "**x**");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::api::Robot;
+use crate::exceptions::APIRequestError;
+use crate::split::{split_payload, MAX_TEXT_LENGTH};
+use serde_json::Value;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use tokio::time::Duration;
+
+pub struct SendMessage<'a> {
+    bot: &'a Robot,
+    payload: Result<Value, APIRequestError>,
+    auto_delete: Option<Duration>,
+    split: Option<bool>,
+}
+
+impl<'a> SendMessage<'a> {
+    pub(crate) fn new(bot: &'a Robot, payload: Value) -> Self {
+        SendMessage {
+            bot,
+            payload: Ok(payload),
+            auto_delete: None,
+            split: None,
+        }
+    }
+
+    pub(crate) fn failed(bot: &'a Robot, error: APIRequestError) -> Self {
+        SendMessage {
+            bot,
+            payload: Err(error),
+            auto_delete: None,
+            split: None,
+        }
+    }
+
+    pub fn auto_delete_after(mut self, delay: Duration) -> Self {
+        self.auto_delete = Some(delay);
+        self
+    }
+
+    pub fn split_long_text(mut self, enabled: bool) -> Self {
+        self.split = Some(enabled);
+        self
+    }
+
+    pub async fn send(self) -> Result<Value, APIRequestError> {
+        let payload = self.payload?;
+        let split = self.split.unwrap_or_else(|| *self.bot.split_long_text.read());
+        let chunks = match split {
+            true => split_payload(&payload, MAX_TEXT_LENGTH, MAX_TEXT_LENGTH),
+            false => vec![payload],
+        };
+        let chat_id = chunks[0].get("chat_id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+
+        let total = chunks.len();
+        let mut responses = Vec::with_capacity(total);
+        for mut chunk in chunks {
+            if let Some(previous) = responses.last().and_then(message_id_of) {
+                chunk["reply_to_message_id"] = Value::String(previous.to_string());
+            }
+            let response = match self.bot.post("sendMessage", &chunk).await {
+                Ok(response) => response,
+                Err(e) if responses.is_empty() => return Err(e),
+                Err(e) => {
+                    return Err(APIRequestError::PartialSend {
+                        sent: responses,
+                        total,
+                        source: Box::new(e),
+                    })
+                }
+            };
+            if let Some(delay) = self.auto_delete {
+                schedule_delete(self.bot, &chat_id, &response, delay);
+            }
+            responses.push(response);
+        }
+
+        Ok(responses.pop().unwrap_or_default())
+    }
+}
+
+impl<'a> IntoFuture for SendMessage<'a> {
+    type Output = Result<Value, APIRequestError>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+pub(crate) fn message_id_of(response: &Value) -> Option<&str> {
+    response.get("data")
+        .and_then(|d| d.get("message_id"))
+        .and_then(|v| v.as_str())
+}
+
+fn schedule_delete(bot: &Robot, chat_id: &str, response: &Value, delay: Duration) {
+    match message_id_of(response) {
+        Some(message_id) => {
+            if let Err(e) = bot.delete_message_after(chat_id, message_id, delay) {
+                crate::logger::log_error(&format!("Failed to schedule deletion of {}: {}", message_id, e));
+            }
+        }
+        None => crate::logger::log_error("Cannot auto-delete message: no message_id in response"),
+    }
+}
@@ -0,0 +1,257 @@
+use crate::rich_text::RichText;
+use serde_json::Value;
+
+pub const MAX_TEXT_LENGTH: usize = 4096;
+pub const MAX_CAPTION_LENGTH: usize = 1024;
+
+const SENTENCE_ENDS: &[char] = &['.', '!', '?', '؟', '۔', '…', '。'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    Paragraph,
+    Line,
+    Sentence,
+    Word,
+}
+
+struct Indexed {
+    chars: Vec<char>,
+    utf16: Vec<usize>,
+}
+
+impl Indexed {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let mut utf16 = Vec::with_capacity(chars.len() + 1);
+        let mut offset = 0;
+        utf16.push(0);
+        for c in &chars {
+            offset += c.len_utf16();
+            utf16.push(offset);
+        }
+        Indexed { chars, utf16 }
+    }
+
+    fn boundary_at(&self, p: usize) -> Option<Boundary> {
+        let prev = *self.chars.get(p.checked_sub(1)?)?;
+        if prev == '\n' {
+            if p >= 2 && self.chars[p - 2] == '\n' {
+                return Some(Boundary::Paragraph);
+            }
+            return Some(Boundary::Line);
+        }
+        if prev.is_whitespace() && prev != '\u{200c}' {
+            if p >= 2 && SENTENCE_ENDS.contains(&self.chars[p - 2]) {
+                return Some(Boundary::Sentence);
+            }
+            return Some(Boundary::Word);
+        }
+        None
+    }
+}
+
+fn is_joining(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F
+        | 0x064B..=0x065F
+        | 0x0670
+        | 0x06D6..=0x06ED
+        | 0x200C..=0x200D
+        | 0xFE00..=0xFE0F
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0020..=0xE007F)
+}
+
+impl RichText {
+    pub fn split(&self, max_len: usize) -> Vec<RichText> {
+        self.split_with(max_len, max_len)
+    }
+
+    pub(crate) fn split_with(&self, first_len: usize, max_len: usize) -> Vec<RichText> {
+        if self.utf16_len() <= first_len {
+            return vec![self.clone()];
+        }
+
+        let index = Indexed::new(self.as_str());
+        let inside_span = |offset: usize| {
+            self.parts().iter().any(|p| p.from_index < offset && offset < p.from_index + p.length)
+        };
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        let n = index.chars.len();
+        while start < n {
+            let max_len = if chunks.is_empty() { first_len } else { max_len }.max(1);
+            let base = index.utf16[start];
+            if index.utf16[n] - base <= max_len {
+                chunks.push(self.slice(&index, start, n));
+                break;
+            }
+
+            let limit = (start..=n).rev().find(|&e| index.utf16[e] - base <= max_len).unwrap_or(start);
+            let min_len = max_len / 3;
+            let candidate = |level: Boundary, respect_spans: bool| {
+                (start + 1..=limit).rev().find(|&p| {
+                    index.utf16[p] - base >= min_len
+                        && index.boundary_at(p).is_some_and(|b| b <= level)
+                        && !(respect_spans && inside_span(index.utf16[p]))
+                })
+            };
+
+            let cut = [Boundary::Paragraph, Boundary::Line, Boundary::Sentence, Boundary::Word]
+                .into_iter()
+                .find_map(|level| candidate(level, true))
+                .or_else(|| candidate(Boundary::Word, false))
+                .unwrap_or_else(|| {
+                    let mut p = limit.max(start + 1);
+                    while p > start + 1 && p < n && is_joining(index.chars[p]) {
+                        p -= 1;
+                    }
+                    p
+                });
+
+            let mut end = cut;
+            while end > start && index.chars[end - 1].is_whitespace() && index.chars[end - 1] != '\u{200c}' {
+                end -= 1;
+            }
+            if end > start {
+                chunks.push(self.slice(&index, start, end));
+            }
+
+            start = cut;
+            while start < n && index.chars[start].is_whitespace() && index.chars[start] != '\u{200c}' {
+                start += 1;
+            }
+        }
+        chunks
+    }
+
+    fn slice(&self, index: &Indexed, start: usize, end: usize) -> RichText {
+        let text: String = index.chars[start..end].iter().collect();
+        let (from, to) = (index.utf16[start], index.utf16[end]);
+        let parts = self.parts().iter()
+            .filter_map(|part| {
+                let part_start = part.from_index.max(from);
+                let part_end = (part.from_index + part.length).min(to);
+                (part_start < part_end).then(|| {
+                    let mut clipped = part.clone();
+                    clipped.from_index = part_start - from;
+                    clipped.length = part_end - part_start;
+                    clipped
+                })
+            })
+            .collect();
+        RichText::from_parts(&text, parts)
+    }
+}
+
+const KEYPAD_FIELDS: &[&str] = &["chat_keypad", "inline_keypad", "chat_keypad_type"];
+
+pub(crate) fn split_payload(payload: &Value, first_len: usize, max_len: usize) -> Vec<Value> {
+    let chunks = rich_text_of(payload).split_with(first_len, max_len);
+    if chunks.len() < 2 {
+        return vec![payload.clone()];
+    }
+
+    let last = chunks.len() - 1;
+    chunks.iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut part = payload.clone();
+            set_rich_text(&mut part, chunk);
+            if i < last {
+                if let Some(map) = part.as_object_mut() {
+                    KEYPAD_FIELDS.iter().for_each(|field| {
+                        map.remove(*field);
+                    });
+                }
+            }
+            part
+        })
+        .collect()
+}
+
+pub(crate) fn rich_text_of(payload: &Value) -> RichText {
+    let text = payload.get("text").and_then(|v| v.as_str()).unwrap_or_default();
+    let parts = payload.get("metadata")
+        .and_then(|m| m.get("meta_data_parts"))
+        .and_then(|p| serde_json::from_value(p.clone()).ok())
+        .unwrap_or_default();
+    RichText::from_parts(text, parts)
+}
+
+pub(crate) fn set_rich_text(payload: &mut Value, text: &RichText) {
+    payload["text"] = Value::String(text.as_str().to_string());
+    match text.metadata() {
+        Some(metadata) => payload["metadata"] = metadata,
+        None => {
+            if let Some(map) = payload.as_object_mut() {
+                map.remove("metadata");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rich_text::{MetaDataPart, MetaType};
+    use serde_json::json;
+
+    fn texts(chunks: &[RichText]) -> Vec<&str> {
+        chunks.iter().map(|c| c.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_short_text_whole() {
+        let text = RichText::new().text("hello");
+        assert_eq!(texts(&text.split(10)), ["hello"]);
+    }
+
+    #[test]
+    fn prefers_paragraph_then_sentence_boundaries() {
+        let text = RichText::new().text("first part\n\nsecond part");
+        assert_eq!(texts(&text.split(16)), ["first part", "second part"]);
+        let text = RichText::new().text("سلام دنیا. حال شما خوب است؟ بله");
+        assert_eq!(texts(&text.split(20)), ["سلام دنیا.", "حال شما خوب است؟ بله"]);
+    }
+
+    #[test]
+    fn chunks_fit_and_cover_the_text() {
+        let source = "کلمه‌ای طولانی و متن فارسی 😀 ".repeat(50);
+        let text = RichText::new().text(&source);
+        let chunks = text.split(100);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.utf16_len() <= 100));
+        let joined: String = texts(&chunks).concat();
+        let strip = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        assert_eq!(strip(&joined), strip(&source));
+    }
+
+    #[test]
+    fn never_starts_a_chunk_with_a_joining_mark() {
+        let text = RichText::new().text("abc\u{064e}def");
+        let chunks = text.split(3);
+        assert_eq!(texts(&chunks)[0], "ab");
+        assert!(chunks.iter().all(|c| !c.as_str().starts_with('\u{064e}')));
+    }
+
+    #[test]
+    fn clips_spans_to_each_chunk() {
+        let text = RichText::new().bold("aaaa bbbb");
+        let chunks = text.split(5);
+        assert_eq!(texts(&chunks), ["aaaa", "bbbb"]);
+        assert_eq!(chunks[0].parts(), &[MetaDataPart::new(MetaType::Bold, 0, 4)]);
+        assert_eq!(chunks[1].parts(), &[MetaDataPart::new(MetaType::Bold, 0, 4)]);
+    }
+
+    #[test]
+    fn keeps_keypads_on_the_last_payload() {
+        let payload = json!({"chat_id": "c", "text": "one two", "inline_keypad": {"rows": []}});
+        let payloads = split_payload(&payload, 4, 4);
+        assert_eq!(payloads.len(), 2);
+        assert!(payloads[0].get("inline_keypad").is_none());
+        assert!(payloads[1].get("inline_keypad").is_some());
+        assert_eq!(payloads[1]["text"], "two");
+    }
+}
@@ -0,0 +1,1105 @@
+use crate::api::Robot;
+use crate::calendar::{CalendarDate, CalendarType, TEHRAN_UTC_OFFSET};
+use crate::context::Keypad;
+use crate::exceptions::APIRequestError;
+use crate::layout::{Layout, LayoutFormat, LayoutKind};
+use crate::rich_text::{escape_markdown, MessageText};
+use crate::send::SendMessage;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+type Translate<'a> = &'a dyn Fn(&str) -> Option<String>;
+type EndTag = Option<(String, String)>;
+
+fn invalid(message: impl Into<String>) -> APIRequestError {
+    APIRequestError::InvalidTemplate(message.into())
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Output(Expr),
+    If {
+        branches: Vec<(Cond, Vec<Node>)>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        var: String,
+        iterable: Expr,
+        body: Vec<Node>,
+        empty: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Path(Vec<String>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+    name: String,
+    args: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+struct Expr {
+    operand: Operand,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    Expr(Expr),
+    Not(Box<Cond>),
+    Compare(Expr, CmpOp, Expr),
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, APIRequestError> {
+        let mut parser = Parser { pieces: scan(source)?, pos: 0 };
+        let (nodes, end) = parser.nodes(&[])?;
+        if let Some((tag, _)) = end {
+            return Err(invalid(format!("unexpected {{% {} %}}", tag)));
+        }
+        Ok(Template { nodes })
+    }
+
+    pub fn render(&self, context: &Value, locale: &str) -> Result<String, APIRequestError> {
+        self.render_with(context, locale, &|_| None)
+    }
+
+    pub fn render_with(&self, context: &Value, locale: &str, translate: Translate<'_>) -> Result<String, APIRequestError> {
+        self.render_inner(context, locale, translate, false)
+    }
+
+    pub fn uses(&self, name: &str) -> bool {
+        nodes_use(&self.nodes, name)
+    }
+
+    fn render_inner(&self, context: &Value, locale: &str, translate: Translate<'_>, escape: bool) -> Result<String, APIRequestError> {
+        let mut renderer = Renderer {
+            root: context,
+            locale,
+            translate,
+            escape,
+            scopes: Vec::new(),
+        };
+        let mut out = String::new();
+        renderer.nodes(&self.nodes, &mut out)?;
+        Ok(out)
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = APIRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::parse(s)
+    }
+}
+
+enum Piece {
+    Text(String),
+    Output(String),
+    Tag(String),
+}
+
+fn scan(source: &str) -> Result<Vec<Piece>, APIRequestError> {
+    let mut pieces = Vec::new();
+    let mut rest = source;
+    let mut line_clean = true;
+
+    loop {
+        let start = ["{{", "{%", "{#"].iter().filter_map(|open| rest.find(open)).min();
+        let Some(start) = start else {
+            if !rest.is_empty() {
+                pieces.push(Piece::Text(rest.to_string()));
+            }
+            return Ok(pieces);
+        };
+
+        let mut text = rest[..start].to_string();
+        let open = &rest[start..start + 2];
+        let close = match open {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let body_start = start + 2;
+        let end = rest[body_start..].find(close)
+            .ok_or_else(|| invalid(format!("unclosed {} at {:?}", open, preview(&rest[start..]))))?;
+        let inner = rest[body_start..body_start + end].trim().to_string();
+        let mut after = &rest[body_start + end + 2..];
+
+        let standalone = open != "{{" && {
+            let line_start = text.rfind('\n').map(|i| i + 1);
+            let prefix_blank = match line_start {
+                Some(i) => text[i..].trim().is_empty(),
+                None => line_clean && text.trim().is_empty(),
+            };
+            let line_end = after.find('\n');
+            let suffix_blank = after[..line_end.unwrap_or(after.len())].trim().is_empty();
+            if prefix_blank && suffix_blank {
+                text.truncate(line_start.unwrap_or(0));
+                after = &after[line_end.map(|i| i + 1).unwrap_or(after.len())..];
+            }
+            prefix_blank && suffix_blank
+        };
+        line_clean = standalone;
+
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        match open {
+            "{{" => pieces.push(Piece::Output(inner)),
+            "{%" => pieces.push(Piece::Tag(inner)),
+            _ => {}
+        }
+        rest = after;
+    }
+}
+
+fn preview(s: &str) -> String {
+    s.chars().take(24).collect()
+}
+
+struct Parser {
+    pieces: Vec<Piece>,
+    pos: usize,
+}
+
+impl Parser {
+    fn nodes(&mut self, until: &[&str]) -> Result<(Vec<Node>, EndTag), APIRequestError> {
+        let mut nodes = Vec::new();
+        while self.pos < self.pieces.len() {
+            let index = self.pos;
+            self.pos += 1;
+            match self.pieces[index] {
+                Piece::Text(ref text) => nodes.push(Node::Text(text.clone())),
+                Piece::Output(ref source) => nodes.push(Node::Output(parse_expr(source)?)),
+                Piece::Tag(ref source) => {
+                    let source = source.clone();
+                    let (keyword, rest) = source.split_once(char::is_whitespace).unwrap_or((&source, ""));
+                    let rest = rest.trim().to_string();
+                    if until.contains(&keyword) {
+                        return Ok((nodes, Some((keyword.to_string(), rest))));
+                    }
+                    match keyword {
+                        "if" => nodes.push(self.if_block(&rest)?),
+                        "for" => nodes.push(self.for_block(&rest)?),
+                        other => return Err(invalid(format!("unexpected {{% {} %}}", other))),
+                    }
+                }
+            }
+        }
+        match until.is_empty() {
+            true => Ok((nodes, None)),
+            false => Err(invalid(format!("missing {{% {} %}}", until[until.len() - 1]))),
+        }
+    }
+
+    fn if_block(&mut self, condition: &str) -> Result<Node, APIRequestError> {
+        let mut branches = Vec::new();
+        let mut condition = parse_cond(condition)?;
+        loop {
+            let (body, end) = self.nodes(&["elif", "else", "endif"])?;
+            branches.push((condition, body));
+            match end {
+                Some((tag, rest)) if tag == "elif" => condition = parse_cond(&rest)?,
+                Some((tag, _)) if tag == "else" => {
+                    let (otherwise, _) = self.nodes(&["endif"])?;
+                    return Ok(Node::If { branches, otherwise });
+                }
+                _ => return Ok(Node::If { branches, otherwise: Vec::new() }),
+            }
+        }
+    }
+
+    fn for_block(&mut self, header: &str) -> Result<Node, APIRequestError> {
+        let (var, iterable) = header.split_once(" in ")
+            .ok_or_else(|| invalid(format!("expected `for name in items`, got {:?}", header)))?;
+        let var = var.trim().to_string();
+        if var.is_empty() || !var.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(invalid(format!("invalid loop variable {:?}", var)));
+        }
+        let iterable = parse_expr(iterable)?;
+        let (body, end) = self.nodes(&["else", "endfor"])?;
+        let empty = match end {
+            Some((tag, _)) if tag == "else" => self.nodes(&["endfor"])?.0,
+            _ => Vec::new(),
+        };
+        Ok(Node::For { var, iterable, body, empty })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 10] = ["==", "!=", "<=", ">=", "<", ">", "|", ":", ",", "!"];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, APIRequestError> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '\'' {
+            let body = &rest[1..];
+            let end = body.find(c).ok_or_else(|| invalid(format!("unterminated string in {:?}", source)))?;
+            tokens.push(Token::Literal(Value::String(body[..end].to_string())));
+            rest = &body[end + 1..];
+        } else if c.is_ascii_digit() || (c == '-' && rest[1..].starts_with(|n: char| n.is_ascii_digit())) {
+            let end = rest[1..].find(|n: char| !n.is_ascii_digit() && n != '.').map(|i| i + 1).unwrap_or(rest.len());
+            let number: Value = serde_json::from_str(&rest[..end])
+                .map_err(|_| invalid(format!("invalid number {:?}", &rest[..end])))?;
+            tokens.push(Token::Literal(number));
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest.find(|n: char| !(n.is_alphanumeric() || n == '_' || n == '.')).unwrap_or(rest.len());
+            tokens.push(match &rest[..end] {
+                "true" => Token::Literal(json!(true)),
+                "false" => Token::Literal(json!(false)),
+                "null" | "none" => Token::Literal(Value::Null),
+                word => Token::Ident(word.to_string()),
+            });
+            rest = &rest[end..];
+        } else {
+            return Err(invalid(format!("unexpected {:?} in {:?}", c, source)));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+    source: String,
+}
+
+impl ExprParser {
+    fn new(source: &str) -> Result<Self, APIRequestError> {
+        Ok(ExprParser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            source: source.to_string(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let matched = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let matched = matches!(self.peek(), Some(Token::Ident(w)) if w == word);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn error(&self) -> APIRequestError {
+        invalid(format!("cannot parse {:?}", self.source))
+    }
+
+    fn finish<T>(&self, value: T) -> Result<T, APIRequestError> {
+        match self.pos >= self.tokens.len() {
+            true => Ok(value),
+            false => Err(self.error()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, APIRequestError> {
+        let operand = match self.next() {
+            Some(Token::Ident(path)) => Operand::Path(path.split('.').map(|s| s.to_string()).collect()),
+            Some(Token::Literal(value)) => Operand::Literal(value),
+            _ => return Err(self.error()),
+        };
+        let mut filters = Vec::new();
+        while self.eat_symbol("|") {
+            let Some(Token::Ident(name)) = self.next() else {
+                return Err(self.error());
+            };
+            let mut args = Vec::new();
+            if self.eat_symbol(":") {
+                loop {
+                    match self.next() {
+                        Some(Token::Literal(value)) => args.push(value),
+                        _ => return Err(self.error()),
+                    }
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+            }
+            filters.push(Filter { name, args });
+        }
+        Ok(Expr { operand, filters })
+    }
+
+    fn or(&mut self) -> Result<Cond, APIRequestError> {
+        let mut cond = self.and()?;
+        while self.eat_word("or") {
+            cond = Cond::Or(Box::new(cond), Box::new(self.and()?));
+        }
+        Ok(cond)
+    }
+
+    fn and(&mut self) -> Result<Cond, APIRequestError> {
+        let mut cond = self.unary()?;
+        while self.eat_word("and") {
+            cond = Cond::And(Box::new(cond), Box::new(self.unary()?));
+        }
+        Ok(cond)
+    }
+
+    fn unary(&mut self) -> Result<Cond, APIRequestError> {
+        if self.eat_word("not") || self.eat_symbol("!") {
+            return Ok(Cond::Not(Box::new(self.unary()?)));
+        }
+        let left = self.expr()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("==")) => CmpOp::Eq,
+            Some(Token::Symbol("!=")) => CmpOp::Ne,
+            Some(Token::Symbol("<")) => CmpOp::Lt,
+            Some(Token::Symbol("<=")) => CmpOp::Le,
+            Some(Token::Symbol(">")) => CmpOp::Gt,
+            Some(Token::Symbol(">=")) => CmpOp::Ge,
+            _ => return Ok(Cond::Expr(left)),
+        };
+        self.pos += 1;
+        Ok(Cond::Compare(left, op, self.expr()?))
+    }
+}
+
+fn parse_expr(source: &str) -> Result<Expr, APIRequestError> {
+    let mut parser = ExprParser::new(source)?;
+    let expr = parser.expr()?;
+    parser.finish(expr)
+}
+
+fn parse_cond(source: &str) -> Result<Cond, APIRequestError> {
+    let mut parser = ExprParser::new(source)?;
+    let cond = parser.or()?;
+    parser.finish(cond)
+}
+
+fn nodes_use(nodes: &[Node], name: &str) -> bool {
+    let expr_uses = |expr: &Expr| matches!(expr.operand, Operand::Path(ref path) if path[0] == name);
+    fn cond_uses(cond: &Cond, expr_uses: &dyn Fn(&Expr) -> bool) -> bool {
+        match cond {
+            Cond::Expr(expr) => expr_uses(expr),
+            Cond::Not(inner) => cond_uses(inner, expr_uses),
+            Cond::Compare(left, _, right) => expr_uses(left) || expr_uses(right),
+            Cond::And(left, right) | Cond::Or(left, right) => cond_uses(left, expr_uses) || cond_uses(right, expr_uses),
+        }
+    }
+    nodes.iter().any(|node| match node {
+        Node::Text(_) => false,
+        Node::Output(expr) => expr_uses(expr),
+        Node::If { branches, otherwise } => {
+            branches.iter().any(|(cond, body)| cond_uses(cond, &expr_uses) || nodes_use(body, name))
+                || nodes_use(otherwise, name)
+        }
+        Node::For { iterable, body, empty, .. } => {
+            expr_uses(iterable) || nodes_use(body, name) || nodes_use(empty, name)
+        }
+    })
+}
+
+struct Renderer<'a> {
+    root: &'a Value,
+    locale: &'a str,
+    translate: Translate<'a>,
+    escape: bool,
+    scopes: Vec<(String, Value)>,
+}
+
+impl Renderer<'_> {
+    fn nodes(&mut self, nodes: &[Node], out: &mut String) -> Result<(), APIRequestError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Output(expr) => {
+                    let raw = expr.filters.iter().any(|f| f.name == "raw" || f.name == "escape");
+                    let text = to_text(&self.eval(expr)?);
+                    match self.escape && !raw {
+                        true => out.push_str(&escape_markdown(&text)),
+                        false => out.push_str(&text),
+                    }
+                }
+                Node::If { branches, otherwise } => {
+                    let mut taken = None;
+                    for (cond, body) in branches {
+                        if self.test(cond)? {
+                            taken = Some(body);
+                            break;
+                        }
+                    }
+                    self.nodes(taken.unwrap_or(otherwise), out)?;
+                }
+                Node::For { var, iterable, body, empty } => {
+                    let items = match self.eval(iterable)? {
+                        Value::Array(items) => items,
+                        Value::Object(map) => map.into_iter()
+                            .map(|(key, value)| json!({"key": key, "value": value}))
+                            .collect(),
+                        Value::Null => Vec::new(),
+                        other => vec![other],
+                    };
+                    if items.is_empty() {
+                        self.nodes(empty, out)?;
+                        continue;
+                    }
+                    let count = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        self.scopes.push((var.clone(), item));
+                        self.scopes.push(("loop".to_string(), json!({
+                            "index": index + 1,
+                            "index0": index,
+                            "first": index == 0,
+                            "last": index + 1 == count,
+                            "length": count,
+                        })));
+                        let result = self.nodes(body, out);
+                        self.scopes.truncate(self.scopes.len() - 2);
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, path: &[String]) -> Value {
+        let (first, rest) = path.split_first().expect("paths are never empty");
+        let base = self.scopes.iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, value)| value)
+            .or_else(|| self.root.get(first));
+        let mut current = match base {
+            Some(value) => value,
+            None => return Value::Null,
+        };
+        for segment in rest {
+            let next = match current {
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                other => other.get(segment),
+            };
+            match next {
+                Some(value) => current = value,
+                None => return Value::Null,
+            }
+        }
+        current.clone()
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<Value, APIRequestError> {
+        let mut value = match expr.operand {
+            Operand::Path(ref path) => self.lookup(path),
+            Operand::Literal(ref value) => value.clone(),
+        };
+        for filter in &expr.filters {
+            value = self.apply(filter, value)?;
+        }
+        Ok(value)
+    }
+
+    fn test(&self, cond: &Cond) -> Result<bool, APIRequestError> {
+        Ok(match cond {
+            Cond::Expr(expr) => truthy(&self.eval(expr)?),
+            Cond::Not(inner) => !self.test(inner)?,
+            Cond::And(left, right) => self.test(left)? && self.test(right)?,
+            Cond::Or(left, right) => self.test(left)? || self.test(right)?,
+            Cond::Compare(left, op, right) => compare(&self.eval(left)?, *op, &self.eval(right)?),
+        })
+    }
+
+    fn apply(&self, filter: &Filter, value: Value) -> Result<Value, APIRequestError> {
+        let arg = |i: usize| filter.args.get(i);
+        let text = || to_text(&value);
+        Ok(match filter.name.as_str() {
+            "raw" => value,
+            "upper" => json!(text().to_uppercase()),
+            "lower" => json!(text().to_lowercase()),
+            "trim" => json!(text().trim()),
+            "escape" => json!(escape_markdown(&text())),
+            "default" => match truthy(&value) {
+                true => value,
+                false => arg(0).cloned().unwrap_or(Value::Null),
+            },
+            "len" | "length" => json!(match value {
+                Value::Array(ref items) => items.len(),
+                Value::Object(ref map) => map.len(),
+                Value::Null => 0,
+                _ => text().chars().count(),
+            }),
+            "join" => {
+                let separator = arg(0).map(to_text).unwrap_or_else(|| ", ".to_string());
+                match value {
+                    Value::Array(items) => json!(items.iter().map(to_text).collect::<Vec<_>>().join(&separator)),
+                    other => other,
+                }
+            }
+            "number" => {
+                let decimals = arg(0).and_then(|v| v.as_u64()).map(|d| d as usize);
+                match as_number(&value) {
+                    Some(number) => json!(format_number(number, decimals, self.locale)),
+                    None => value,
+                }
+            }
+            "digits" => json!(localize_digits(&text(), self.locale)),
+            "date" | "datetime" => {
+                let style = arg(0).and_then(|v| v.as_str()).unwrap_or("short");
+                let with_time = filter.name == "datetime";
+                match format_value_date(&value, style, with_time, self.locale) {
+                    Some(formatted) => json!(formatted),
+                    None => value,
+                }
+            }
+            "t" => json!((self.translate)(&text()).unwrap_or_else(text)),
+            other => return Err(invalid(format!("unknown filter {:?}", other))),
+        })
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn compare(left: &Value, op: CmpOp, right: &Value) -> bool {
+    let ordering = match (as_number(left), as_number(right)) {
+        (Some(a), Some(b)) if !left.is_string() || !right.is_string() => a.partial_cmp(&b),
+        _ => Some(to_text(left).cmp(&to_text(right))),
+    };
+    let Some(ordering) = ordering else {
+        return op == CmpOp::Ne;
+    };
+    match op {
+        CmpOp::Eq => ordering.is_eq(),
+        CmpOp::Ne => ordering.is_ne(),
+        CmpOp::Lt => ordering.is_lt(),
+        CmpOp::Le => ordering.is_le(),
+        CmpOp::Gt => ordering.is_gt(),
+        CmpOp::Ge => ordering.is_ge(),
+    }
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+struct NumberSymbols {
+    zero: char,
+    group: &'static str,
+    decimal: &'static str,
+}
+
+fn number_symbols(locale: &str) -> NumberSymbols {
+    match locale.split(['-', '_']).next().unwrap_or(locale) {
+        "fa" => NumberSymbols { zero: '۰', group: "٬", decimal: "٫" },
+        "ar" => NumberSymbols { zero: '٠', group: "٬", decimal: "٫" },
+        _ => NumberSymbols { zero: '0', group: ",", decimal: "." },
+    }
+}
+
+pub fn localize_digits(text: &str, locale: &str) -> String {
+    let zero = number_symbols(locale).zero;
+    text.chars()
+        .map(|c| match c.to_digit(10) {
+            Some(d) if c.is_ascii_digit() => char::from_u32(zero as u32 + d).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+pub fn format_number(value: f64, decimals: Option<usize>, locale: &str) -> String {
+    let symbols = number_symbols(locale);
+    let plain = match decimals {
+        Some(decimals) => format!("{:.*}", decimals, value),
+        None if value.fract() == 0.0 && value.abs() < 1e15 => format!("{}", value as i64),
+        None => value.to_string(),
+    };
+    let (sign, digits) = match plain.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", plain.as_str()),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push_str(symbols.group);
+        }
+        grouped.push(c);
+    }
+    if !fraction.is_empty() {
+        grouped.push_str(symbols.decimal);
+        grouped.push_str(fraction);
+    }
+    localize_digits(&format!("{}{}", sign, grouped), locale)
+}
+
+pub fn format_date(date: &CalendarDate, style: &str, locale: &str) -> String {
+    let date = match locale.split(['-', '_']).next() {
+        Some("fa") => date.to_persian(),
+        _ => date.to_gregorian(),
+    };
+    let formatted = match (style, date.calendar) {
+        ("long", _) => format!("{} {} {}", date.day, date.month_name(), date.year),
+        (_, CalendarType::Persian) => format!("{:04}/{:02}/{:02}", date.year, date.month, date.day),
+        (_, CalendarType::Gregorian) => date.to_string(),
+    };
+    localize_digits(&formatted, locale)
+}
+
+fn format_value_date(value: &Value, style: &str, with_time: bool, locale: &str) -> Option<String> {
+    let offset = match locale.split(['-', '_']).next() {
+        Some("fa") => TEHRAN_UTC_OFFSET,
+        _ => 0,
+    };
+    let (date, seconds) = match value {
+        Value::Number(n) => {
+            let local = n.as_i64()? + offset;
+            (CalendarDate::from_days(local.div_euclid(86_400), CalendarType::Gregorian), Some(local.rem_euclid(86_400)))
+        }
+        Value::String(s) => {
+            let (day, time) = s.split_once(['T', ' ']).unwrap_or((s, ""));
+            let seconds = time.split(':').take(2).map(|p| p.parse::<i64>().ok()).collect::<Option<Vec<_>>>()
+                .filter(|parts| parts.len() == 2)
+                .map(|parts| parts[0] * 3600 + parts[1] * 60);
+            (CalendarDate::parse(day, CalendarType::Gregorian)?, seconds)
+        }
+        _ => return None,
+    };
+
+    let mut formatted = format_date(&date, style, locale);
+    if let (true, Some(seconds)) = (with_time, seconds) {
+        let time = format!(" {:02}:{:02}", seconds / 3600, seconds % 3600 / 60);
+        formatted.push_str(&localize_digits(&time, locale));
+    }
+    Some(formatted)
+}
+
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub text: MessageText,
+    pub keypad: Option<Keypad>,
+    pub kind: LayoutKind,
+}
+
+impl Rendered {
+    pub fn send<'a>(&self, bot: &'a Robot, chat_id: &str, reply_to_message_id: Option<&str>) -> SendMessage<'a> {
+        let (chat_keypad, inline_keypad, chat_keypad_type) = match (self.kind, self.keypad.as_ref()) {
+            (LayoutKind::Chat, Some(keypad)) => (Some(keypad), None, Some("New")),
+            (LayoutKind::Inline, Some(keypad)) => (None, Some(keypad), None),
+            (_, None) => (None, None, None),
+        };
+        bot.send_message(
+            chat_id,
+            self.text.clone(),
+            chat_keypad,
+            inline_keypad,
+            false,
+            reply_to_message_id,
+            chat_keypad_type,
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TemplateText {
+    Single(String),
+    Localized(HashMap<String, String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateSpec {
+    text: TemplateText,
+    #[serde(default)]
+    fallback_locale: Option<String>,
+    #[serde(default)]
+    markdown: bool,
+    #[serde(default)]
+    keypad: Option<Layout>,
+}
+
+#[derive(Debug, Clone)]
+struct NamedTemplate {
+    texts: HashMap<String, Template>,
+    fallback_locale: Option<String>,
+    markdown: bool,
+    keypad: Option<KeypadTemplate>,
+}
+
+#[derive(Debug, Clone)]
+struct KeypadTemplate {
+    kind: LayoutKind,
+    layout: Value,
+    strings: Vec<Template>,
+}
+
+impl KeypadTemplate {
+    fn compile(layout: &Layout) -> Result<Self, APIRequestError> {
+        let mut value = serde_json::to_value(layout)?;
+        let mut strings = Vec::new();
+        walk_strings(&mut value, &mut |s| {
+            strings.push(Template::parse(s)?);
+            Ok(())
+        })?;
+        Ok(KeypadTemplate { kind: layout.kind, layout: value, strings })
+    }
+
+    fn render(&self, context: &Value, locale: &str, translate: Translate<'_>) -> Result<Keypad, APIRequestError> {
+        let mut value = self.layout.clone();
+        let mut strings = self.strings.iter();
+        walk_strings(&mut value, &mut |s| {
+            let template = strings.next().ok_or_else(|| invalid("keypad changed after compiling"))?;
+            *s = template.render_with(context, locale, translate)?;
+            Ok(())
+        })?;
+        let layout: Layout = serde_json::from_value(value)?;
+        layout.render_unchecked(&[], translate)
+    }
+}
+
+impl NamedTemplate {
+    fn compile(spec: TemplateSpec) -> Result<Self, APIRequestError> {
+        let texts = match spec.text {
+            TemplateText::Single(source) => HashMap::from([("default".to_string(), Template::parse(&source)?)]),
+            TemplateText::Localized(sources) => sources.iter()
+                .map(|(locale, source)| Ok((locale.clone(), Template::parse(source)?)))
+                .collect::<Result<_, APIRequestError>>()?,
+        };
+        if let Some(ref locale) = spec.fallback_locale {
+            if !texts.contains_key(locale) {
+                return Err(invalid(format!("fallback locale {} has no text", locale)));
+            }
+        }
+        let keypad = spec.keypad.as_ref().map(KeypadTemplate::compile).transpose()?;
+        Ok(NamedTemplate {
+            texts,
+            fallback_locale: spec.fallback_locale,
+            markdown: spec.markdown,
+            keypad,
+        })
+    }
+
+    fn text_for(&self, locale: &str) -> Result<&Template, APIRequestError> {
+        let base = locale.split(['-', '_']).next().unwrap_or(locale);
+        [Some(locale), Some(base), Some("default"), self.fallback_locale.as_deref()].into_iter()
+            .flatten()
+            .find_map(|l| self.texts.get(l))
+            .ok_or_else(|| invalid(format!("no text for locale {} and no default or fallback_locale", locale)))
+    }
+
+    fn uses(&self, name: &str) -> bool {
+        self.texts.values().any(|t| t.uses(name))
+            || self.keypad.as_ref().is_some_and(|k| k.strings.iter().any(|t| t.uses(name)))
+    }
+
+    fn render(&self, context: &Value, locale: &str, translate: Translate<'_>) -> Result<Rendered, APIRequestError> {
+        let template = self.text_for(locale)?;
+        let body = template.render_inner(context, locale, translate, self.markdown)?;
+        let text = match self.markdown {
+            true => MessageText::markdown(&body),
+            false => MessageText::plain(&body),
+        };
+
+        let keypad = self.keypad.as_ref().map(|k| k.render(context, locale, translate)).transpose()?;
+        let kind = self.keypad.as_ref().map(|k| k.kind).unwrap_or_default();
+        Ok(Rendered { text, keypad, kind })
+    }
+}
+
+fn walk_strings<F>(value: &mut Value, f: &mut F) -> Result<(), APIRequestError>
+where
+    F: FnMut(&mut String) -> Result<(), APIRequestError>,
+{
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter_mut().try_for_each(|v| walk_strings(v, f)),
+        Value::Object(map) => map.values_mut().try_for_each(|v| walk_strings(v, f)),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Default)]
+struct TemplateSet {
+    templates: HashMap<String, NamedTemplate>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    inner: Arc<RwLock<TemplateSet>>,
+}
+
+impl Templates {
+    pub fn parse(content: &str, format: LayoutFormat) -> Result<Self, APIRequestError> {
+        let templates = parse_templates(content, format)?;
+        Ok(Templates {
+            inner: Arc::new(RwLock::new(TemplateSet { templates, path: None, modified: None })),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, APIRequestError> {
+        let path = path.as_ref().to_path_buf();
+        let (templates, modified) = read_templates(&path)?;
+        Ok(Templates {
+            inner: Arc::new(RwLock::new(TemplateSet {
+                templates,
+                path: Some(path),
+                modified,
+            })),
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.inner.read().templates.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.read().templates.contains_key(name)
+    }
+
+    pub fn uses(&self, name: &str, variable: &str) -> bool {
+        self.inner.read().templates.get(name).is_some_and(|t| t.uses(variable))
+    }
+
+    pub fn render(&self, name: &str, context: &Value, locale: &str) -> Result<Rendered, APIRequestError> {
+        self.render_with(name, context, locale, &|_| None)
+    }
+
+    pub fn render_with(
+        &self,
+        name: &str,
+        context: &Value,
+        locale: &str,
+        translate: Translate<'_>,
+    ) -> Result<Rendered, APIRequestError> {
+        let template = self.inner.read().templates.get(name).cloned()
+            .ok_or_else(|| invalid(format!("unknown template {}", name)))?;
+        template.render(context, locale, translate)
+    }
+
+    pub fn reload(&self) -> Result<(), APIRequestError> {
+        let path = match self.inner.read().path.clone() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let (templates, modified) = read_templates(&path)?;
+        let mut inner = self.inner.write();
+        inner.templates = templates;
+        inner.modified = modified;
+        Ok(())
+    }
+
+    pub fn reload_if_changed(&self) -> Result<bool, APIRequestError> {
+        let (path, known) = {
+            let inner = self.inner.read();
+            match inner.path.clone() {
+                Some(path) => (path, inner.modified),
+                None => return Ok(false),
+            }
+        };
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == known {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    pub fn watch(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let templates = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let current = templates.clone();
+                let reloaded = tokio::task::spawn_blocking(move || current.reload_if_changed())
+                    .await
+                    .unwrap_or_else(|e| Err(APIRequestError::RequestFailed(e.to_string())));
+                match reloaded {
+                    Ok(true) => crate::logger::log_info("Reloaded message templates"),
+                    Ok(false) => {}
+                    Err(e) => crate::logger::log_error(&format!("Keeping previous message templates: {}", e)),
+                }
+            }
+        })
+    }
+}
+
+fn read_templates(path: &Path) -> Result<(HashMap<String, NamedTemplate>, Option<SystemTime>), APIRequestError> {
+    let format = LayoutFormat::from_path(path).ok_or_else(|| {
+        APIRequestError::RequestFailed(format!("Unknown template file format: {}", path.display()))
+    })?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| APIRequestError::RequestFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok((parse_templates(&content, format)?, modified))
+}
+
+fn parse_templates(content: &str, format: LayoutFormat) -> Result<HashMap<String, NamedTemplate>, APIRequestError> {
+    let specs: HashMap<String, TemplateSpec> = match format {
+        LayoutFormat::Json => serde_json::from_str(content)?,
+        LayoutFormat::Toml => toml::from_str(content).map_err(|e| invalid(e.to_string()))?,
+        LayoutFormat::Yaml => serde_yaml::from_str(content).map_err(|e| invalid(e.to_string()))?,
+    };
+    specs.into_iter()
+        .map(|(name, spec)| {
+            let template = NamedTemplate::compile(spec).map_err(|e| match e {
+                APIRequestError::InvalidTemplate(message) => invalid(format!("{}: {}", name, message)),
+                other => other,
+            })?;
+            Ok((name, template))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, context: Value, locale: &str) -> String {
+        Template::parse(source).unwrap().render(&context, locale).unwrap()
+    }
+
+    #[test]
+    fn renders_variables_and_filters() {
+        let context = json!({"user": {"name": "ali"}, "tags": ["a", "b"]});
+        assert_eq!(render("Hi {{ user.name | upper }}!", context.clone(), "en"), "Hi ALI!");
+        assert_eq!(render("{{ missing | default:\"guest\" }}", context.clone(), "en"), "guest");
+        assert_eq!(render("{{ tags | join:\", \" }} ({{ tags | len }})", context, "en"), "a, b (2)");
+    }
+
+    #[test]
+    fn localizes_numbers_for_persian() {
+        assert_eq!(render("{{ n | number }}", json!({"n": 1234567}), "en"), "1,234,567");
+        assert_eq!(render("{{ n | number }}", json!({"n": 1234567}), "fa"), "۱٬۲۳۴٬۵۶۷");
+        assert_eq!(localize_digits("2025", "fa"), "۲۰۲۵");
+    }
+
+    #[test]
+    fn evaluates_conditions() {
+        let source = "{% if vip and balance > 100 %}gold{% elif vip %}silver{% else %}basic{% endif %}";
+        assert_eq!(render(source, json!({"vip": true, "balance": 500}), "en"), "gold");
+        assert_eq!(render(source, json!({"vip": true, "balance": 5}), "en"), "silver");
+        assert_eq!(render(source, json!({"vip": false}), "en"), "basic");
+        assert_eq!(render("{% if not name %}anon{% endif %}", json!({}), "en"), "anon");
+    }
+
+    #[test]
+    fn loops_with_loop_variables() {
+        let source = "{% for item in items %}{{ loop.index }}.{{ item }}{% if not loop.last %} {% endif %}{% else %}none{% endfor %}";
+        assert_eq!(render(source, json!({"items": ["x", "y"]}), "en"), "1.x 2.y");
+        assert_eq!(render(source, json!({"items": []}), "en"), "none");
+    }
+
+    #[test]
+    fn trims_standalone_tag_lines() {
+        let source = "list:\n{% for i in items %}\n- {{ i }}\n{% endfor %}\ndone";
+        assert_eq!(render(source, json!({"items": [1, 2]}), "en"), "list:\n- 1\n- 2\ndone");
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert!(Template::parse("{% if x %}open").is_err());
+        assert!(Template::parse("{{ name").is_err());
+        assert!(Template::parse("{% endfor %}").is_err());
+        assert!(Template::parse("{{ x | }}").is_err());
+        assert!(Template::parse("{{ name }}").unwrap().uses("name"));
+    }
+
+    #[test]
+    fn keypad_uses_only_double_brace_placeholders() {
+        let templates = Templates::parse(
+            r#"{"menu": {"text": "hi", "keypad": {"kind": "inline", "rows": [[
+                {"id": "a", "type": "Simple", "text": "{{ name }} {name}"}
+            ]]}}}"#,
+            LayoutFormat::Json,
+        ).unwrap();
+        let rendered = templates.render("menu", &json!({"name": "Ali"}), "en").unwrap();
+        let keypad = rendered.keypad.unwrap();
+        assert_eq!(keypad.rows[0].buttons[0].button_text.as_deref(), Some("Ali {name}"));
+    }
+
+    #[test]
+    fn uses_checks_keypad_strings() {
+        let templates = Templates::parse(
+            r#"{"menu": {"text": "hi", "keypad": {"kind": "inline", "rows": [[
+                {"id": "a", "type": "Simple", "text": "{{ user_name }}"}
+            ]]}}}"#,
+            LayoutFormat::Json,
+        ).unwrap();
+        assert!(templates.uses("menu", "user_name"));
+        assert!(!templates.uses("menu", "balance"));
+    }
+}
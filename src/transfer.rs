@@ -0,0 +1,228 @@
+use crate::exceptions::APIRequestError;
+use bytes::Bytes;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+pub type TransferCallback = Arc<dyn Fn(TransferProgress) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    pub total: Option<u64>,
+}
+
+impl TransferProgress {
+    pub fn percent(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(100.0),
+            Some(total) => Some(self.transferred as f64 * 100.0 / total as f64),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, base_delay: Duration, max_delay: Duration) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+            max_delay,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO)
+    }
+
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(4, Duration::from_secs(1), Duration::from_secs(30))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Transfer {
+    callback: Option<TransferCallback>,
+    watch: Option<Arc<watch::Sender<TransferProgress>>>,
+    cancel: CancellationToken,
+}
+
+impl Transfer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(TransferProgress) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn subscribe(&mut self) -> watch::Receiver<TransferProgress> {
+        match self.watch {
+            Some(ref tx) => tx.subscribe(),
+            None => {
+                let (tx, rx) = watch::channel(TransferProgress::default());
+                self.watch = Some(Arc::new(tx));
+                rx
+            }
+        }
+    }
+
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub(crate) fn report(&self, progress: TransferProgress) {
+        if let Some(ref callback) = self.callback {
+            callback(progress);
+        }
+        if let Some(ref tx) = self.watch {
+            tx.send_replace(progress);
+        }
+    }
+
+    pub(crate) async fn run<F, T>(&self, fut: F) -> Result<T, APIRequestError>
+    where
+        F: std::future::Future<Output = Result<T, APIRequestError>>,
+    {
+        tokio::select! {
+            _ = self.cancel.cancelled() => Err(APIRequestError::Cancelled),
+            result = fut => result,
+        }
+    }
+
+    pub(crate) fn track<S>(&self, stream: S, total: Option<u64>) -> ProgressStream<S>
+    where
+        S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+    {
+        self.report(TransferProgress { transferred: 0, total });
+        ProgressStream {
+            inner: stream,
+            transfer: self.clone(),
+            progress: TransferProgress { transferred: 0, total },
+        }
+    }
+}
+
+impl std::fmt::Debug for Transfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transfer")
+            .field("has_callback", &self.callback.is_some())
+            .field("has_watch", &self.watch.is_some())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+pub struct ProgressStream<S> {
+    inner: S,
+    transfer: Transfer,
+    progress: TransferProgress,
+}
+
+impl<S> Stream for ProgressStream<S>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    type Item = std::io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.transfer.is_cancelled() {
+            return Poll::Ready(Some(Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "transfer cancelled",
+            ))));
+        }
+
+        let item = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(Ok(ref chunk))) = item {
+            self.progress.transferred += chunk.len() as u64;
+            let progress = self.progress;
+            self.transfer.report(progress);
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{stream, StreamExt};
+    use parking_lot::Mutex;
+
+    fn chunks(sizes: &[usize]) -> impl Stream<Item = std::io::Result<Bytes>> + Unpin {
+        stream::iter(sizes.iter().map(|&n| Ok(Bytes::from(vec![0u8; n]))).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn reports_progress_per_chunk() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let mut transfer = Transfer::new().on_progress(move |p| log.lock().push(p.transferred));
+        let rx = transfer.subscribe();
+
+        let received: Vec<_> = transfer.track(chunks(&[10, 20, 5]), Some(35)).collect().await;
+        assert_eq!(received.len(), 3);
+        assert_eq!(*seen.lock(), vec![0, 10, 30, 35]);
+        assert_eq!(*rx.borrow(), TransferProgress { transferred: 35, total: Some(35) });
+        assert_eq!(rx.borrow().percent(), Some(100.0));
+        assert_eq!(TransferProgress { transferred: 5, total: None }.percent(), None);
+    }
+
+    #[tokio::test]
+    async fn cancellation_stops_the_stream_and_the_request() {
+        let transfer = Transfer::new();
+        let mut tracked = transfer.track(chunks(&[4, 4, 4]), None);
+        assert!(tracked.next().await.unwrap().is_ok());
+
+        transfer.cancel_token().cancel();
+        let error = tracked.next().await.unwrap().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Interrupted);
+
+        let pending = transfer.run(std::future::pending::<Result<(), APIRequestError>>()).await;
+        assert!(matches!(pending, Err(APIRequestError::Cancelled)));
+        assert!(Transfer::new().run(async { Ok(1) }).await.is_ok());
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy::new(5, Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<_> = (1..=5).map(|a| policy.delay_for(a).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+        assert_eq!(RetryPolicy::new(0, Duration::ZERO, Duration::ZERO).max_attempts, 1);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::context::{Button, ButtonType, Keypad};
+use crate::exceptions::APIRequestError;
+use std::collections::HashMap;
+
+pub const MAX_BUTTONS_PER_ROW: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeypadProblem {
+    EmptyKeypad,
+    EmptyRow,
+    TooManyButtons { count: usize, max: usize },
+    MissingId,
+    DuplicateId { first_row: usize, first_column: usize },
+    MissingText,
+    MissingOptions(ButtonType),
+    InvalidNumber { field: &'static str, value: String },
+    NumberRange { min: String, max: String },
+    NumberDefaultOutOfRange { default: String },
+    EmptyPickerItems,
+    PickerDefaultNotInItems { default: String },
+    YearRange { min_year: String, max_year: String },
+    InvalidLinkUrl(String),
+}
+
+impl std::fmt::Display for KeypadProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeypadProblem::EmptyKeypad => write!(f, "keypad has no rows"),
+            KeypadProblem::EmptyRow => write!(f, "row has no buttons"),
+            KeypadProblem::TooManyButtons { count, max } => {
+                write!(f, "row has {} buttons (max {})", count, max)
+            }
+            KeypadProblem::MissingId => write!(f, "button has no id"),
+            KeypadProblem::DuplicateId { first_row, first_column } => {
+                write!(f, "id already used at row {}, column {}", first_row, first_column)
+            }
+            KeypadProblem::MissingText => write!(f, "button has no text"),
+            KeypadProblem::MissingOptions(type_) => write!(f, "{} button has no options", type_),
+            KeypadProblem::InvalidNumber { field, value } => {
+                write!(f, "{} is not a number: {:?}", field, value)
+            }
+            KeypadProblem::NumberRange { min, max } => {
+                write!(f, "min_value {} is greater than max_value {}", min, max)
+            }
+            KeypadProblem::NumberDefaultOutOfRange { default } => {
+                write!(f, "default_value {} is outside the picker range", default)
+            }
+            KeypadProblem::EmptyPickerItems => write!(f, "string picker has no items"),
+            KeypadProblem::PickerDefaultNotInItems { default } => {
+                write!(f, "default_value {:?} is not one of the items", default)
+            }
+            KeypadProblem::YearRange { min_year, max_year } => {
+                write!(f, "min_year {} is greater than max_year {}", min_year, max_year)
+            }
+            KeypadProblem::InvalidLinkUrl(url) => write!(f, "invalid link url {:?}", url),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeypadIssue {
+    pub row: usize,
+    pub column: Option<usize>,
+    pub button_id: Option<String>,
+    pub problem: KeypadProblem,
+}
+
+impl std::fmt::Display for KeypadIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.column, &self.button_id) {
+            (Some(column), Some(id)) => write!(f, "row {}, column {} ({}): {}", self.row, column, id, self.problem),
+            (Some(column), None) => write!(f, "row {}, column {}: {}", self.row, column, self.problem),
+            _ => write!(f, "row {}: {}", self.row, self.problem),
+        }
+    }
+}
+
+pub(crate) fn describe(issues: &[KeypadIssue]) -> String {
+    issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+impl Keypad {
+    pub fn validate(&self) -> Result<(), Vec<KeypadIssue>> {
+        let mut issues = Vec::new();
+        let mut seen: HashMap<&str, (usize, usize)> = HashMap::new();
+
+        if self.rows.is_empty() {
+            issues.push(KeypadIssue { row: 0, column: None, button_id: None, problem: KeypadProblem::EmptyKeypad });
+        }
+
+        for (r, row) in self.rows.iter().enumerate() {
+            if row.buttons.is_empty() {
+                issues.push(KeypadIssue { row: r, column: None, button_id: None, problem: KeypadProblem::EmptyRow });
+            }
+            if row.buttons.len() > MAX_BUTTONS_PER_ROW {
+                issues.push(KeypadIssue {
+                    row: r,
+                    column: None,
+                    button_id: None,
+                    problem: KeypadProblem::TooManyButtons { count: row.buttons.len(), max: MAX_BUTTONS_PER_ROW },
+                });
+            }
+
+            for (c, button) in row.buttons.iter().enumerate() {
+                let mut problems = button_problems(button);
+                match button.id.as_deref() {
+                    Some(id) if !id.is_empty() => {
+                        if let Some(&(first_row, first_column)) = seen.get(id) {
+                            problems.push(KeypadProblem::DuplicateId { first_row, first_column });
+                        } else {
+                            seen.insert(id, (r, c));
+                        }
+                    }
+                    _ => problems.push(KeypadProblem::MissingId),
+                }
+                issues.extend(problems.into_iter().map(|problem| KeypadIssue {
+                    row: r,
+                    column: Some(c),
+                    button_id: button.id.clone(),
+                    problem,
+                }));
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    pub fn check(&self) -> Result<(), APIRequestError> {
+        self.validate().map_err(APIRequestError::InvalidKeypad)
+    }
+}
+
+fn button_problems(button: &Button) -> Vec<KeypadProblem> {
+    let mut problems = Vec::new();
+
+    if button.button_text.as_deref().is_none_or(|t| t.trim().is_empty()) {
+        problems.push(KeypadProblem::MissingText);
+    }
+
+    match button.type_ {
+        ButtonType::NumberPicker => match button.button_number_picker {
+            Some(ref picker) => {
+                let min = parse_number(&mut problems, "min_value", picker.min_value.as_deref());
+                let max = parse_number(&mut problems, "max_value", picker.max_value.as_deref());
+                let default = parse_number(&mut problems, "default_value", picker.default_value.as_deref());
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        problems.push(KeypadProblem::NumberRange {
+                            min: min.to_string(),
+                            max: max.to_string(),
+                        });
+                    } else if let Some(default) = default.filter(|d| *d < min || *d > max) {
+                        problems.push(KeypadProblem::NumberDefaultOutOfRange { default: default.to_string() });
+                    }
+                }
+            }
+            None => problems.push(KeypadProblem::MissingOptions(button.type_)),
+        },
+        ButtonType::StringPicker => match button.button_string_picker {
+            Some(ref picker) => {
+                if picker.items.is_empty() {
+                    problems.push(KeypadProblem::EmptyPickerItems);
+                }
+                if let Some(ref default) = picker.default_value {
+                    if !picker.items.contains(default) {
+                        problems.push(KeypadProblem::PickerDefaultNotInItems { default: default.clone() });
+                    }
+                }
+            }
+            None => problems.push(KeypadProblem::MissingOptions(button.type_)),
+        },
+        ButtonType::Calendar => match button.button_calendar {
+            Some(ref calendar) => {
+                let min = parse_number(&mut problems, "min_year", calendar.min_year.as_deref());
+                let max = parse_number(&mut problems, "max_year", calendar.max_year.as_deref());
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        problems.push(KeypadProblem::YearRange {
+                            min_year: min.to_string(),
+                            max_year: max.to_string(),
+                        });
+                    }
+                }
+            }
+            None => problems.push(KeypadProblem::MissingOptions(button.type_)),
+        },
+        ButtonType::Link => {
+            let url = button.url.as_deref().unwrap_or_default();
+            let valid = reqwest::Url::parse(url)
+                .map(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some())
+                .unwrap_or(false);
+            if !valid {
+                problems.push(KeypadProblem::InvalidLinkUrl(url.to_string()));
+            }
+        }
+        ButtonType::Selection if button.button_selection.is_none() => {
+            problems.push(KeypadProblem::MissingOptions(button.type_));
+        }
+        ButtonType::Location if button.button_location.is_none() => {
+            problems.push(KeypadProblem::MissingOptions(button.type_));
+        }
+        ButtonType::Textbox if button.button_textbox.is_none() => {
+            problems.push(KeypadProblem::MissingOptions(button.type_));
+        }
+        ButtonType::Payment if button.button_payment.is_none() => {
+            problems.push(KeypadProblem::MissingOptions(button.type_));
+        }
+        _ => {}
+    }
+
+    problems
+}
+
+fn parse_number(problems: &mut Vec<KeypadProblem>, field: &'static str, value: Option<&str>) -> Option<i64> {
+    let value = value?;
+    match value.trim().parse::<i64>() {
+        Ok(n) => Some(n),
+        Err(_) => {
+            problems.push(KeypadProblem::InvalidNumber { field, value: value.to_string() });
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::button::InlineBuilder;
+    use crate::calendar::CalendarType;
+
+    fn problems(keypad: &Keypad) -> Vec<KeypadProblem> {
+        keypad.validate().err().unwrap_or_default().into_iter().map(|i| i.problem).collect()
+    }
+
+    #[test]
+    fn accepts_a_valid_keypad() {
+        let keypad = InlineBuilder::new()
+            .row(&[Button::simple("a", "A"), Button::simple("b", "B")])
+            .build();
+        assert!(keypad.is_ok());
+    }
+
+    #[test]
+    fn reports_empty_rows_and_duplicate_ids() {
+        let keypad = InlineBuilder::new()
+            .row(&[])
+            .row(&[Button::simple("a", "A"), Button::simple("a", "Again")])
+            .build_unchecked();
+        let issues = keypad.validate().unwrap_err();
+        assert_eq!(issues[0].problem, KeypadProblem::EmptyRow);
+        assert_eq!(issues[1].row, 1);
+        assert_eq!(issues[1].column, Some(1));
+        assert_eq!(issues[1].problem, KeypadProblem::DuplicateId { first_row: 1, first_column: 0 });
+        assert!(matches!(keypad.check(), Err(APIRequestError::InvalidKeypad(_))));
+    }
+
+    #[test]
+    fn limits_buttons_per_row() {
+        let buttons: Vec<Button> = (0..=MAX_BUTTONS_PER_ROW).map(|i| Button::simple(&i.to_string(), "x")).collect();
+        let keypad = InlineBuilder::new().row(&buttons).build_unchecked();
+        assert_eq!(problems(&keypad), [KeypadProblem::TooManyButtons { count: 9, max: MAX_BUTTONS_PER_ROW }]);
+    }
+
+    #[test]
+    fn checks_picker_and_link_options() {
+        let builder = InlineBuilder::new();
+        let numbers = builder.button_number_picker("n", "N", "10", "1", None);
+        let strings = builder.button_string_picker("s", Some("S"), &["a".to_string()], Some("b"));
+        let years = builder.button_calendar("c", "C", CalendarType::Persian, None, Some(1405), Some(1400));
+        let link = builder.button_link("l", "L", "not a url");
+        let keypad = builder.row(&[numbers, strings]).row(&[years, link]).build_unchecked();
+        assert_eq!(problems(&keypad), [
+            KeypadProblem::NumberRange { min: "10".to_string(), max: "1".to_string() },
+            KeypadProblem::PickerDefaultNotInItems { default: "b".to_string() },
+            KeypadProblem::YearRange { min_year: "1405".to_string(), max_year: "1400".to_string() },
+            KeypadProblem::InvalidLinkUrl("not a url".to_string()),
+        ]);
+    }
+}